@@ -1,7 +1,8 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::{FRAC_PI_2, PI};
 
-// #[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Direction {
     North,
     East,
@@ -26,6 +27,41 @@ impl Direction {
             Direction::West => Vec2::new(-1.0, 0.0),
         }
     }
+
+    /// rotation of a sprite drawn facing North
+    pub fn to_rotation(&self) -> Quat {
+        let angle = match self {
+            Direction::North => 0.0,       // up = sprite par défaut
+            Direction::East => -FRAC_PI_2, // right = -90°
+            Direction::South => PI,        // down = 180°
+            Direction::West => FRAC_PI_2,  // left = +90°
+        };
+        Quat::from_rotation_z(angle)
+    }
+
+    pub fn opposite(&self) -> Self {
+        match self {
+            Direction::North => Direction::South,
+            Direction::East => Direction::West,
+            Direction::South => Direction::North,
+            Direction::West => Direction::East,
+        }
+    }
+
+    /// quarter turn clockwise
+    pub fn turned_right(&self) -> Self {
+        match self {
+            Direction::North => Direction::East,
+            Direction::East => Direction::South,
+            Direction::South => Direction::West,
+            Direction::West => Direction::North,
+        }
+    }
+
+    /// quarter turn counterclockwise
+    pub fn turned_left(&self) -> Self {
+        self.turned_right().opposite()
+    }
 }
 
 impl Default for Direction {
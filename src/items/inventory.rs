@@ -1,16 +1,18 @@
-use crate::items::{ItemType, Quality};
+use crate::items::{ItemType, Quality, registry::ItemRegistry};
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::mem::replace;
 
 // const DEFAULT_ITEM_STACK_LIMIT: u32 = 10000;
 // const DEFAULT_INVENTORY_SLOTS_QUANTITY_LIMIT: u32 = 10;
-const DEFAULT_ITEM_STACK_LIMIT: u32 = 10;
+/// stack size of the items missing from the ItemRegistry
+pub const DEFAULT_ITEM_STACK_LIMIT: u32 = 10;
 const DEFAULT_INVENTORY_SLOTS_QUANTITY_LIMIT: u32 = 5;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ItemStack {
     pub item_type: ItemType,
+    #[serde(default)]
     pub quality: Quality,
     pub quantity: u32,
 }
@@ -23,10 +25,13 @@ impl ItemStack {
         }
     }
 
-    pub fn can_stack_with(&self, other: &Self) -> bool {
-        self.item_type == other.item_type
-            && self.quality == other.quality
-            && (self.quantity + other.quantity) <= DEFAULT_ITEM_STACK_LIMIT
+    pub fn is_same_item(&self, other: &Self) -> bool {
+        self.item_type == other.item_type && self.quality == other.quality
+    }
+
+    pub fn can_stack_with(&self, other: &Self, item_registry: &ItemRegistry) -> bool {
+        self.is_same_item(other)
+            && (self.quantity + other.quantity) <= item_registry.stack_size(self.item_type)
     }
 }
 
@@ -36,64 +41,176 @@ pub struct Inventory {
     pub slots_quantity_limit: u32,
 }
 impl Inventory {
-    pub fn add(&mut self, item_stack: ItemStack) -> Result<(), ()> {
-        // TODO: see if make that we fill existing slots as much as possible event if everything wont fit BUT we have to make sure it's ok to not add everything
+    /// an empty inventory of slots_quantity_limit slots
+    pub fn with_slots(slots_quantity_limit: u32) -> Self {
+        Self {
+            slots: Vec::new(),
+            slots_quantity_limit,
+        }
+    }
+
+    /// adds nothing if everything can't fit, see insert() to add as much as possible
+    pub fn add(&mut self, item_stack: ItemStack, item_registry: &ItemRegistry) -> Result<(), ()> {
+        if !self.enough_room(item_stack, item_registry) {
+            return Err(());
+        }
+        self.insert(item_stack, item_registry);
+        Ok(())
+    }
+
+    /// fills existing compatible slots first then empty slots, returns what didn't fit
+    pub fn insert(
+        &mut self,
+        item_stack: ItemStack,
+        item_registry: &ItemRegistry,
+    ) -> Option<ItemStack> {
+        let stack_size = item_registry.stack_size(item_stack.item_type);
+        let mut quantity_left = item_stack.quantity;
         for slot in self.slots.iter_mut() {
-            if slot.can_stack_with(&item_stack) {
-                slot.quantity += item_stack.quantity;
-                return Ok(());
+            if quantity_left == 0 {
+                break;
+            }
+            if slot.is_same_item(&item_stack) && slot.quantity < stack_size {
+                let quantity_added = quantity_left.min(stack_size - slot.quantity);
+                slot.quantity += quantity_added;
+                quantity_left -= quantity_added;
             }
         }
-        // if no compatible slot found, add to an empty slot
-        if self.slots.len() < self.slots_quantity_limit as usize {
-            self.slots.push(item_stack);
-            return Ok(());
+        while quantity_left > 0 && self.slots.len() < self.slots_quantity_limit as usize {
+            let quantity_added = quantity_left.min(stack_size);
+            self.slots.push(ItemStack {
+                quantity: quantity_added,
+                ..item_stack
+            });
+            quantity_left -= quantity_added;
+        }
+
+        (quantity_left > 0).then_some(ItemStack {
+            quantity: quantity_left,
+            ..item_stack
+        })
+    }
+
+    /// moves as many items as possible to other, what doesn't fit stays here
+    pub fn transfer_all_to(&mut self, other: &mut Inventory, item_registry: &ItemRegistry) {
+        for slot in self.slots.iter_mut() {
+            slot.quantity = other
+                .insert(*slot, item_registry)
+                .map_or(0, |leftover| leftover.quantity);
         }
-        Err(())
+        self.slots.retain(|slot| slot.quantity > 0);
+    }
+
+    /// moves as much as possible of the slot at index to other, returns false if nothing moved
+    pub fn transfer_slot_to(
+        &mut self,
+        index: usize,
+        other: &mut Inventory,
+        item_registry: &ItemRegistry,
+    ) -> bool {
+        let Some(slot) = self.slots.get_mut(index) else {
+            return false;
+        };
+        let quantity_left = other
+            .insert(*slot, item_registry)
+            .map_or(0, |leftover| leftover.quantity);
+        let moved = quantity_left < slot.quantity;
+        slot.quantity = quantity_left;
+        self.slots.retain(|slot| slot.quantity > 0);
+        moved
+    }
+
+    /// quantity of the item that can still be added, in existing compatible slots and in empty slots
+    pub fn room_for(&self, item_stack: ItemStack, item_registry: &ItemRegistry) -> u32 {
+        let stack_size = item_registry.stack_size(item_stack.item_type);
+        let room_in_existing_slots: u32 = self
+            .slots
+            .iter()
+            .filter(|slot| slot.is_same_item(&item_stack))
+            .map(|slot| stack_size.saturating_sub(slot.quantity))
+            .sum();
+        let empty_slots = (self.slots_quantity_limit as usize).saturating_sub(self.slots.len());
+        room_in_existing_slots + empty_slots as u32 * stack_size
     }
 
     pub fn remove_all_item_stack(&mut self) -> Vec<ItemStack> {
         replace(&mut self.slots, Vec::new())
     }
 
-    /// checks all slots to see if there A UNIQ SLOT is enough quantity of specifil ItemType and Quality
+    /// total quantity of the item over all slots
+    pub fn count(&self, item_type: ItemType, quality: Quality) -> u32 {
+        self.slots
+            .iter()
+            .filter(|slot| slot.item_type == item_type && slot.quality == quality)
+            .map(|slot| slot.quantity)
+            .sum()
+    }
+
+    pub fn count_any_quality(&self, item_type: ItemType) -> u32 {
+        self.slots
+            .iter()
+            .filter(|slot| slot.item_type == item_type)
+            .map(|slot| slot.quantity)
+            .sum()
+    }
+
+    /// checks if all slots together hold enough quantity of the ItemType and Quality
     pub fn enough_quantity(&self, item_stack: ItemStack) -> bool {
-        for slot in self.slots.iter() {
-            if slot.item_type == item_stack.item_type
-                && slot.quality == item_stack.quality
-                && slot.quantity >= item_stack.quantity
-            {
-                return true;
-            }
-        }
-        false
+        self.count(item_stack.item_type, item_stack.quality) >= item_stack.quantity
     }
 
-    /// returns true if there is at least an empty slot or a slot of same type and quality with enough room for the desired quantity to add
-    pub fn enough_room(&self, item_stack: ItemStack) -> bool {
-        if self.slots.len() < self.slots_quantity_limit as usize {
-            return true;
-        }
-        for slot in self.slots.iter() {
-            if slot.can_stack_with(&item_stack) {
-                return true;
+    /// returns true if the whole quantity fits, spread over existing slots of same type and quality and empty slots
+    pub fn enough_room(&self, item_stack: ItemStack, item_registry: &ItemRegistry) -> bool {
+        self.room_for(item_stack, item_registry) >= item_stack.quantity
+    }
+
+    /// takes up to item_stack.quantity from as many slots as needed, starting with the last ones, returns what was taken
+    pub fn remove(&mut self, item_stack: ItemStack) -> ItemStack {
+        let mut quantity_left = item_stack.quantity;
+        for slot in self.slots.iter_mut().rev() {
+            if quantity_left == 0 {
+                break;
+            }
+            if slot.is_same_item(&item_stack) {
+                let quantity_removed = quantity_left.min(slot.quantity);
+                slot.quantity -= quantity_removed;
+                quantity_left -= quantity_removed;
             }
         }
-        false
-    }
+        self.slots.retain(|slot| slot.quantity > 0);
 
-    pub fn remove_quantity(&mut self, item_stack: ItemStack) {
-        if let Some(pos) = self.slots.iter().position(|slot| {
-            slot.item_type == item_stack.item_type
-                && slot.quality == item_stack.quality
-                && slot.quantity >= item_stack.quantity
-        }) {
-            self.slots[pos].quantity -= item_stack.quantity;
+        ItemStack {
+            quantity: item_stack.quantity - quantity_left,
+            ..item_stack
+        }
+    }
 
-            if self.slots[pos].quantity <= 0 {
-                self.slots.remove(pos);
+    /// takes up to quantity of the item whatever its quality, starting with the first slots, returns what was taken
+    pub fn remove_any_quality(&mut self, item_type: ItemType, quantity: u32) -> Vec<ItemStack> {
+        let mut removed: Vec<ItemStack> = Vec::new();
+        let mut quantity_left = quantity;
+        for slot in self.slots.iter_mut() {
+            if quantity_left == 0 {
+                break;
+            }
+            if slot.item_type == item_type {
+                let quantity_removed = quantity_left.min(slot.quantity);
+                slot.quantity -= quantity_removed;
+                quantity_left -= quantity_removed;
+                match removed
+                    .iter_mut()
+                    .find(|item_stack| item_stack.quality == slot.quality)
+                {
+                    Some(item_stack) => item_stack.quantity += quantity_removed,
+                    None => removed.push(ItemStack {
+                        quantity: quantity_removed,
+                        ..*slot
+                    }),
+                }
             }
         }
+        self.slots.retain(|slot| slot.quantity > 0);
+        removed
     }
 }
 impl Default for Inventory {
@@ -108,129 +225,212 @@ impl Default for Inventory {
 pub struct InputInventory(pub Inventory);
 #[derive(Component, Default)]
 pub struct OutputInventory(pub Inventory);
+/// items burnt to produce energy, only items with a fuel value go in
+#[derive(Component, Default)]
+pub struct FuelInventory(pub Inventory);
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::items::registry::ItemDefinition;
+
+    /// every item stacks up to DEFAULT_ITEM_STACK_LIMIT, except copper wires which stack up to 50
+    fn test_item_registry() -> ItemRegistry {
+        let definitions = [
+            ItemType::IRON_ORE,
+            ItemType::COPPER_ORE,
+            ItemType::IRON_PLATE,
+            ItemType::IRON_GEAR,
+            ItemType::COPPER_WIRE,
+        ]
+        .into_iter()
+        .map(|item_type| {
+            ItemDefinition::test(
+                item_type,
+                if item_type == ItemType::COPPER_WIRE {
+                    50
+                } else {
+                    DEFAULT_ITEM_STACK_LIMIT
+                },
+            )
+        })
+        .collect();
+        ItemRegistry::from_definitions(definitions).unwrap()
+    }
 
     // ItemStack
     #[test]
     fn test_can_stack_with() {
+        let item_registry = test_item_registry();
         let item_stack = ItemStack {
-            item_type: ItemType::IronOre,
+            item_type: ItemType::IRON_ORE,
             quality: Quality::Standard,
             quantity: DEFAULT_ITEM_STACK_LIMIT - 1,
         };
 
         let other = ItemStack {
-            item_type: ItemType::IronOre,
+            item_type: ItemType::IRON_ORE,
             quality: Quality::Standard,
             quantity: 1,
         };
-        assert!(item_stack.can_stack_with(&other));
+        assert!(item_stack.can_stack_with(&other, &item_registry));
 
         let other_too_much = ItemStack {
-            item_type: ItemType::IronOre,
+            item_type: ItemType::IRON_ORE,
             quality: Quality::Standard,
             quantity: 2,
         };
-        assert!(!item_stack.can_stack_with(&other_too_much));
+        assert!(!item_stack.can_stack_with(&other_too_much, &item_registry));
 
         let other_different_item_type = ItemStack {
-            item_type: ItemType::IronGear,
+            item_type: ItemType::IRON_GEAR,
             quality: Quality::Standard,
             quantity: 1,
         };
-        assert!(!item_stack.can_stack_with(&other_different_item_type));
+        assert!(!item_stack.can_stack_with(&other_different_item_type, &item_registry));
 
         let other_different_quality = ItemStack {
-            item_type: ItemType::IronGear,
+            item_type: ItemType::IRON_GEAR,
             quality: Quality::Standard,
             quantity: 1,
         };
-        assert!(!item_stack.can_stack_with(&other_different_quality));
+        assert!(!item_stack.can_stack_with(&other_different_quality, &item_registry));
     }
 
     // Inventory
     #[test]
     fn test_add() {
+        let item_registry = test_item_registry();
         let mut inventory = Inventory::default();
 
         assert_eq!(inventory.slots.len(), 0);
         let item_stack = ItemStack {
-            item_type: ItemType::IronOre,
+            item_type: ItemType::IRON_ORE,
             quality: Quality::Standard,
             quantity: 1,
         };
-        assert!(inventory.add(item_stack).is_ok());
+        assert!(inventory.add(item_stack, &item_registry).is_ok());
         assert_eq!(inventory.slots.len(), 1);
 
         // tries to fill existing compatible slots first
-        assert!(inventory.add(item_stack).is_ok());
+        assert!(inventory.add(item_stack, &item_registry).is_ok());
         assert_eq!(inventory.slots.len(), 1);
         assert_eq!(inventory.slots.get(0).unwrap().quantity, 2);
 
         // add to a free slot if existing compatible slots are full
         let item_stack_fill_existing_slot = ItemStack {
-            item_type: ItemType::IronOre,
+            item_type: ItemType::IRON_ORE,
             quality: Quality::Standard,
             quantity: DEFAULT_ITEM_STACK_LIMIT - 2,
         };
-        assert!(inventory.add(item_stack_fill_existing_slot).is_ok());
+        assert!(
+            inventory
+                .add(item_stack_fill_existing_slot, &item_registry)
+                .is_ok()
+        );
         assert_eq!(inventory.slots.len(), 1);
         assert_eq!(
             inventory.slots.get(0).unwrap().quantity,
             DEFAULT_ITEM_STACK_LIMIT
         );
-        assert!(inventory.add(item_stack).is_ok());
+        assert!(inventory.add(item_stack, &item_registry).is_ok());
         assert_eq!(inventory.slots.len(), 2);
         let item_stack_different_type = ItemStack {
-            item_type: ItemType::CopperOre,
+            item_type: ItemType::COPPER_ORE,
             quality: Quality::Standard,
             quantity: 1,
         };
-        assert!(inventory.add(item_stack_different_type).is_ok());
+        assert!(
+            inventory
+                .add(item_stack_different_type, &item_registry)
+                .is_ok()
+        );
         assert_eq!(inventory.slots.len(), 3);
         let item_stack_different_quality = ItemStack {
-            item_type: ItemType::CopperOre,
+            item_type: ItemType::COPPER_ORE,
             quality: Quality::Perfect,
             quantity: 1,
         };
-        assert!(inventory.add(item_stack_different_quality).is_ok());
+        assert!(
+            inventory
+                .add(item_stack_different_quality, &item_registry)
+                .is_ok()
+        );
         assert_eq!(inventory.slots.len(), 4);
 
         // can add up to inventory.slots_quantity_limit slots
         let item_stack_fill_empty_slot = ItemStack {
-            item_type: ItemType::IronPlate,
+            item_type: ItemType::IRON_PLATE,
             quality: Quality::Standard,
             quantity: DEFAULT_ITEM_STACK_LIMIT,
         };
         for _ in inventory.slots.len()..inventory.slots_quantity_limit as usize - 1 {
-            assert!(inventory.add(item_stack_fill_empty_slot).is_ok());
+            assert!(
+                inventory
+                    .add(item_stack_fill_empty_slot, &item_registry)
+                    .is_ok()
+            );
         }
         assert_eq!(
             inventory.slots.len(),
             inventory.slots_quantity_limit as usize - 1
         );
-        assert!(inventory.add(item_stack_fill_empty_slot).is_ok());
+        assert!(
+            inventory
+                .add(item_stack_fill_empty_slot, &item_registry)
+                .is_ok()
+        );
         assert_eq!(
             inventory.slots.len(),
             inventory.slots_quantity_limit as usize
         );
         let item_stack_too_much_quantity = ItemStack {
-            item_type: ItemType::IronPlate,
+            item_type: ItemType::IRON_PLATE,
             quality: Quality::Standard,
             quantity: DEFAULT_ITEM_STACK_LIMIT * 10,
         };
-        assert!(inventory.add(item_stack_too_much_quantity).is_err());
+        assert!(
+            inventory
+                .add(item_stack_too_much_quantity, &item_registry)
+                .is_err()
+        );
 
         // don't add if there is no more free slots nor existing compatible slots
         let item_stack_new_category = ItemStack {
-            item_type: ItemType::CopperWire,
+            item_type: ItemType::COPPER_WIRE,
             quality: Quality::Defective,
             quantity: 1,
         };
-        assert!(inventory.add(item_stack_new_category).is_err());
+        assert!(
+            inventory
+                .add(item_stack_new_category, &item_registry)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_add_uses_item_stack_size() {
+        let item_registry = test_item_registry();
+        let mut inventory = Inventory::default();
+
+        // more than DEFAULT_ITEM_STACK_LIMIT fits in one slot when the item stacks higher
+        let copper_wires = ItemStack::new(ItemType::COPPER_WIRE, Quality::Standard, 40);
+        assert!(inventory.add(copper_wires, &item_registry).is_ok());
+        assert_eq!(inventory.slots, vec![copper_wires]);
+
+        // an incoming stack is split over the partially filled slot and new slots
+        let iron_ore = ItemStack::new(ItemType::IRON_ORE, Quality::Standard, 8);
+        assert!(inventory.add(iron_ore, &item_registry).is_ok());
+        let more_iron_ore = ItemStack::new(ItemType::IRON_ORE, Quality::Standard, 15);
+        assert!(inventory.add(more_iron_ore, &item_registry).is_ok());
+        let quantities: Vec<_> = inventory.slots.iter().map(|slot| slot.quantity).collect();
+        assert_eq!(quantities, vec![40, 10, 10, 3]);
+
+        // nothing is added when everything doesn't fit
+        assert_eq!(inventory.room_for(iron_ore, &item_registry), 17);
+        let too_much_iron_ore = ItemStack::new(ItemType::IRON_ORE, Quality::Standard, 18);
+        assert!(inventory.add(too_much_iron_ore, &item_registry).is_err());
+        assert_eq!(inventory.slots.len(), 4);
     }
 
     #[test]
@@ -238,48 +438,58 @@ mod tests {
         let mut inventory = Inventory::default();
 
         let item_stack = ItemStack {
-            item_type: ItemType::IronOre,
+            item_type: ItemType::IRON_ORE,
             quality: Quality::Standard,
             quantity: 1,
         };
         assert!(!inventory.enough_quantity(item_stack));
 
         inventory.slots.push(ItemStack {
-            item_type: ItemType::IronOre,
+            item_type: ItemType::IRON_ORE,
             quality: Quality::Standard,
             quantity: 1,
         });
         assert!(inventory.enough_quantity(item_stack));
 
         let item_stack_wrong_type = ItemStack {
-            item_type: ItemType::CopperOre,
+            item_type: ItemType::COPPER_ORE,
             quality: Quality::Standard,
             quantity: 1,
         };
         assert!(!inventory.enough_quantity(item_stack_wrong_type));
 
         let item_stack_wrong_quality = ItemStack {
-            item_type: ItemType::IronOre,
+            item_type: ItemType::IRON_ORE,
             quality: Quality::Perfect,
             quantity: 1,
         };
         assert!(!inventory.enough_quantity(item_stack_wrong_quality));
+
+        // quantities of several slots add up
+        inventory.slots.push(item_stack);
+        let item_stack_over_two_slots = ItemStack {
+            item_type: ItemType::IRON_ORE,
+            quality: Quality::Standard,
+            quantity: 2,
+        };
+        assert!(inventory.enough_quantity(item_stack_over_two_slots));
     }
 
     #[test]
     fn test_enough_room() {
+        let item_registry = test_item_registry();
         let mut inventory = Inventory::default();
 
         let item_stack = ItemStack {
-            item_type: ItemType::IronOre,
+            item_type: ItemType::IRON_ORE,
             quality: Quality::Standard,
             quantity: 1,
         };
-        assert!(inventory.enough_room(item_stack));
+        assert!(inventory.enough_room(item_stack, &item_registry));
 
         // leave only 2 empty slots and add 2 special item stack to test ItemType and Quality
         let item_stack_fill_empty_slot = ItemStack {
-            item_type: ItemType::IronPlate,
+            item_type: ItemType::IRON_PLATE,
             quality: Quality::Standard,
             quantity: DEFAULT_ITEM_STACK_LIMIT,
         };
@@ -287,13 +497,13 @@ mod tests {
             inventory.slots.push(item_stack_fill_empty_slot);
         }
         let item_stack_different_type = ItemStack {
-            item_type: ItemType::CopperOre,
+            item_type: ItemType::COPPER_ORE,
             quality: Quality::Standard,
             quantity: 1,
         };
         inventory.slots.push(item_stack_different_type);
         let item_stack_different_quality = ItemStack {
-            item_type: ItemType::CopperOre,
+            item_type: ItemType::COPPER_ORE,
             quality: Quality::Perfect,
             quantity: 1,
         };
@@ -304,36 +514,136 @@ mod tests {
         );
 
         // enough room when no empty slots BUT existing compatible slots
-        assert!(inventory.enough_room(item_stack_different_type));
-        assert!(inventory.enough_room(item_stack_different_quality));
+        assert!(inventory.enough_room(item_stack_different_type, &item_registry));
+        assert!(inventory.enough_room(item_stack_different_quality, &item_registry));
 
         // not enough room when no empty slots or existing compatible slots
         let item_stack_different_type_and_quality = ItemStack {
-            item_type: ItemType::CopperWire,
+            item_type: ItemType::COPPER_WIRE,
             quality: Quality::Defective,
             quantity: 1,
         };
-        assert!(!inventory.enough_room(item_stack_different_type_and_quality));
+        assert!(!inventory.enough_room(item_stack_different_type_and_quality, &item_registry));
     }
 
     #[test]
-    fn test_remove_quantity() {
+    fn test_remove() {
         let mut inventory = Inventory::default();
 
         let item_stack = ItemStack {
-            item_type: ItemType::IronOre,
+            item_type: ItemType::IRON_ORE,
             quality: Quality::Standard,
             quantity: 2,
         };
         inventory.slots.push(item_stack);
         assert_eq!(inventory.slots.get(0).unwrap().quantity, 2);
         let item_stack_quantity_to_remove = ItemStack {
-            item_type: ItemType::IronOre,
+            item_type: ItemType::IRON_ORE,
             quality: Quality::Standard,
             quantity: 1,
         };
-        inventory.remove_quantity(item_stack_quantity_to_remove);
+        inventory.remove(item_stack_quantity_to_remove);
         assert_eq!(inventory.slots.get(0).unwrap().quantity, 1);
+
+        // takes from several slots and empties them
+        inventory.slots.push(item_stack);
+        let item_stack_over_two_slots = ItemStack::new(ItemType::IRON_ORE, Quality::Standard, 2);
+        assert_eq!(
+            inventory.remove(item_stack_over_two_slots),
+            item_stack_over_two_slots
+        );
+        assert_eq!(inventory.count(ItemType::IRON_ORE, Quality::Standard), 1);
+        assert_eq!(inventory.slots.len(), 1);
+
+        // returns only what was there
+        let removed = inventory.remove(ItemStack::new(ItemType::IRON_ORE, Quality::Standard, 5));
+        assert_eq!(removed.quantity, 1);
+        assert!(inventory.slots.is_empty());
+    }
+
+    #[test]
+    fn test_remove_any_quality() {
+        let mut inventory = Inventory {
+            slots: vec![
+                ItemStack::new(ItemType::IRON_PLATE, Quality::Perfect, 2),
+                ItemStack::new(ItemType::IRON_ORE, Quality::Standard, 5),
+                ItemStack::new(ItemType::IRON_PLATE, Quality::Defective, 2),
+                ItemStack::new(ItemType::IRON_PLATE, Quality::Perfect, 2),
+            ],
+            ..default()
+        };
+        assert_eq!(inventory.count_any_quality(ItemType::IRON_PLATE), 6);
+
+        let removed = inventory.remove_any_quality(ItemType::IRON_PLATE, 5);
+        assert_eq!(
+            removed,
+            vec![
+                ItemStack::new(ItemType::IRON_PLATE, Quality::Perfect, 3),
+                ItemStack::new(ItemType::IRON_PLATE, Quality::Defective, 2),
+            ]
+        );
+        assert_eq!(inventory.count_any_quality(ItemType::IRON_PLATE), 1);
+        assert_eq!(inventory.slots.len(), 2);
+    }
+
+    #[test]
+    fn test_insert_returns_leftover() {
+        let item_registry = test_item_registry();
+        let mut inventory = Inventory {
+            slots: Vec::new(),
+            slots_quantity_limit: 2,
+        };
+
+        let iron_ore = ItemStack::new(ItemType::IRON_ORE, Quality::Standard, 15);
+        assert_eq!(inventory.insert(iron_ore, &item_registry), None);
+        assert_eq!(
+            inventory.insert(iron_ore, &item_registry),
+            Some(ItemStack::new(ItemType::IRON_ORE, Quality::Standard, 10))
+        );
+        assert_eq!(
+            inventory.count(ItemType::IRON_ORE, Quality::Standard),
+            2 * DEFAULT_ITEM_STACK_LIMIT
+        );
+
+        // transfer_all_to() keeps what doesn't fit
+        let mut other = Inventory {
+            slots: Vec::new(),
+            slots_quantity_limit: 1,
+        };
+        inventory.transfer_all_to(&mut other, &item_registry);
+        assert_eq!(
+            other.count(ItemType::IRON_ORE, Quality::Standard),
+            DEFAULT_ITEM_STACK_LIMIT
+        );
+        assert_eq!(
+            inventory.count(ItemType::IRON_ORE, Quality::Standard),
+            DEFAULT_ITEM_STACK_LIMIT
+        );
+    }
+
+    #[test]
+    fn test_transfer_slot_to() {
+        let item_registry = test_item_registry();
+        let mut inventory = Inventory {
+            slots: vec![
+                ItemStack::new(ItemType::IRON_ORE, Quality::Standard, 5),
+                ItemStack::new(ItemType::IRON_PLATE, Quality::Standard, 8),
+            ],
+            ..default()
+        };
+        let mut other = Inventory {
+            slots: vec![ItemStack::new(ItemType::IRON_PLATE, Quality::Standard, 6)],
+            slots_quantity_limit: 1,
+        };
+
+        // only what fits is moved, the rest stays in its slot
+        assert!(inventory.transfer_slot_to(1, &mut other, &item_registry));
+        assert_eq!(other.count(ItemType::IRON_PLATE, Quality::Standard), 10);
+        assert_eq!(inventory.count(ItemType::IRON_PLATE, Quality::Standard), 4);
+
+        assert!(!inventory.transfer_slot_to(0, &mut other, &item_registry));
+        assert!(!inventory.transfer_slot_to(5, &mut other, &item_registry));
+        assert_eq!(inventory.slots.len(), 2);
     }
 }
 // REGARDER pourquoi ça ajouter pas d'items dans l'output des mining machine
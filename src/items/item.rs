@@ -1,15 +1,82 @@
-use serde::{Deserialize, Serialize};
+use crate::items::inventory::ItemStack;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    collections::HashSet,
+    fmt,
+    sync::{LazyLock, Mutex},
+};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
-pub enum ItemType {
-    IronOre,
-    CopperOre,
+/// ids read from asset files are interned so the types refering to them stay Copy, each distinct id is leaked once
+pub(crate) fn intern(id: &str) -> &'static str {
+    static INTERNED_IDS: LazyLock<Mutex<HashSet<&'static str>>> = LazyLock::new(Default::default);
+
+    let mut interned_ids = INTERNED_IDS.lock().unwrap();
+    match interned_ids.get(id) {
+        Some(interned_id) => interned_id,
+        None => {
+            let interned_id: &'static str = Box::leak(id.into());
+            interned_ids.insert(interned_id);
+            interned_id
+        }
+    }
+}
+
+/// id of an item definition of the ItemRegistry, items themselves are defined in assets/items/
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ItemType(&'static str);
+impl ItemType {
+    // items the code refers to directly, they must exist in the item definitions
+    pub const IRON_ORE: Self = Self("iron_ore");
+    pub const COPPER_ORE: Self = Self("copper_ore");
+    pub const COAL: Self = Self("coal");
 
-    IronPlate,
-    CopperPlate,
+    pub const IRON_PLATE: Self = Self("iron_plate");
+    pub const COPPER_PLATE: Self = Self("copper_plate");
+
+    pub const IRON_GEAR: Self = Self("iron_gear");
+    pub const COPPER_WIRE: Self = Self("copper_wire");
+
+    pub const WALL: Self = Self("wall");
+    pub const BELT: Self = Self("belt");
+    pub const INSERTER: Self = Self("inserter");
+    pub const CHEST: Self = Self("chest");
+    pub const CRAFTING_MACHINE: Self = Self("crafting_machine");
+    pub const MINING_MACHINE: Self = Self("mining_machine");
+    pub const BURNER_CRAFTING_MACHINE: Self = Self("burner_crafting_machine");
+    pub const BURNER_MINING_MACHINE: Self = Self("burner_mining_machine");
+    pub const FURNACE: Self = Self("furnace");
+    pub const POWER_POLE: Self = Self("power_pole");
+    pub const SOLAR_PANEL: Self = Self("solar_panel");
+    pub const BURNER_GENERATOR: Self = Self("burner_generator");
+
+    pub fn new(id: &str) -> Self {
+        Self(intern(id))
+    }
 
-    IronGear,
-    CopperWire,
+    pub fn id(&self) -> &'static str {
+        self.0
+    }
+}
+impl fmt::Debug for ItemType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+impl fmt::Display for ItemType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+impl Serialize for ItemType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.0)
+    }
+}
+impl<'de> Deserialize<'de> for ItemType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let id = String::deserialize(deserializer)?;
+        Ok(Self::new(&id))
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -24,3 +91,78 @@ impl Default for Quality {
         Quality::Standard
     }
 }
+impl Quality {
+    fn rank(self) -> u32 {
+        match self {
+            Quality::Defective => 0,
+            Quality::Standard => 1,
+            Quality::Perfect => 2,
+        }
+    }
+
+    fn from_rank(rank: u32) -> Self {
+        match rank {
+            0 => Quality::Defective,
+            1 => Quality::Standard,
+            _ => Quality::Perfect,
+        }
+    }
+
+    pub fn upgraded(self) -> Self {
+        Self::from_rank(self.rank() + 1)
+    }
+
+    pub fn downgraded(self) -> Self {
+        Self::from_rank(self.rank().saturating_sub(1))
+    }
+
+    /// rounded average quality of the items weighted by their quantity, Standard when there is no item
+    pub fn average(item_stacks: &[ItemStack]) -> Self {
+        let quantity: u32 = item_stacks
+            .iter()
+            .map(|item_stack| item_stack.quantity)
+            .sum();
+        if quantity == 0 {
+            return Quality::Standard;
+        }
+        let rank_sum: u32 = item_stacks
+            .iter()
+            .map(|item_stack| item_stack.quality.rank() * item_stack.quantity)
+            .sum();
+        Self::from_rank((rank_sum + quantity / 2) / quantity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quality_average() {
+        let item_stack =
+            |quality, quantity| ItemStack::new(ItemType::IRON_PLATE, quality, quantity);
+
+        assert_eq!(Quality::average(&[]), Quality::Standard);
+        assert_eq!(
+            Quality::average(&[item_stack(Quality::Perfect, 2)]),
+            Quality::Perfect
+        );
+        assert_eq!(
+            Quality::average(&[
+                item_stack(Quality::Perfect, 1),
+                item_stack(Quality::Defective, 1)
+            ]),
+            Quality::Standard
+        );
+        // weighted by quantity
+        assert_eq!(
+            Quality::average(&[
+                item_stack(Quality::Perfect, 3),
+                item_stack(Quality::Defective, 1)
+            ]),
+            Quality::Perfect
+        );
+        assert_eq!(Quality::Perfect.upgraded(), Quality::Perfect);
+        assert_eq!(Quality::Defective.downgraded(), Quality::Defective);
+    }
+}
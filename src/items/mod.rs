@@ -1,5 +1,7 @@
 pub mod inventory;
 mod item;
+pub mod production;
 pub mod recipe;
+pub mod registry;
 
 pub use item::*;
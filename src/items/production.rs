@@ -0,0 +1,274 @@
+use crate::{
+    FixedSet,
+    items::{ItemType, inventory::ItemStack},
+    loading::LoadingState,
+    map::structure::machine::{process_crafting_machines_system, process_mining_machines_system},
+    time::GameTime,
+    units::mining::player_manual_mining_system,
+};
+use bevy::prelude::*;
+use std::{
+    collections::{BTreeSet, HashMap, VecDeque},
+    fmt::Write,
+};
+
+pub const PATH_PRODUCTION_CSV: &str = "exports/production_stats.csv";
+
+pub struct ProductionPlugin;
+impl Plugin for ProductionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ProductionStats>().add_systems(
+            FixedUpdate,
+            // the bucket of the tick is started before anything is recorded in it
+            advance_production_stats_system
+                .before(process_crafting_machines_system)
+                .before(process_mining_machines_system)
+                .before(player_manual_mining_system)
+                .in_set(FixedSet::Process)
+                .run_if(in_state(LoadingState::Ready)),
+        );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProductionPeriod {
+    #[default]
+    Minute,
+    Hour,
+}
+impl ProductionPeriod {
+    pub const ALL: [Self; 2] = [Self::Minute, Self::Hour];
+
+    pub fn bucket_ticks(&self) -> u64 {
+        match self {
+            Self::Minute => GameTime::TICKS_PER_SECOND * 60,
+            Self::Hour => GameTime::TICKS_PER_SECOND * 60 * 60,
+        }
+    }
+}
+
+/// items counted during one period of game time
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProductionBucket {
+    pub start_tick: u64,
+    pub produced: HashMap<ItemType, u32>,
+    pub consumed: HashMap<ItemType, u32>,
+}
+
+/// the last buckets of a period, oldest first, the last one is being filled
+#[derive(Debug, Clone)]
+pub struct ProductionHistory {
+    pub period: ProductionPeriod,
+    pub max_buckets: usize,
+    pub buckets: VecDeque<ProductionBucket>,
+}
+impl ProductionHistory {
+    pub fn new(period: ProductionPeriod, max_buckets: usize) -> Self {
+        Self {
+            period,
+            max_buckets,
+            buckets: VecDeque::new(),
+        }
+    }
+
+    /// starts the bucket of the tick, the periods without production get empty buckets
+    pub fn advance(&mut self, tick: u64) {
+        let bucket_ticks = self.period.bucket_ticks();
+        let start_tick = tick - tick % bucket_ticks;
+        // game time went back, after loading a save
+        if self
+            .buckets
+            .back()
+            .is_some_and(|bucket| bucket.start_tick > start_tick)
+        {
+            self.buckets.clear();
+        }
+
+        let oldest_kept_tick =
+            start_tick.saturating_sub(bucket_ticks * (self.max_buckets as u64 - 1));
+        let mut next_start_tick = self
+            .buckets
+            .back()
+            .map_or(start_tick, |bucket| bucket.start_tick + bucket_ticks)
+            .max(oldest_kept_tick);
+        while next_start_tick <= start_tick {
+            self.buckets.push_back(ProductionBucket {
+                start_tick: next_start_tick,
+                ..default()
+            });
+            next_start_tick += bucket_ticks;
+        }
+        while self.buckets.len() > self.max_buckets {
+            self.buckets.pop_front();
+        }
+    }
+
+    fn current_bucket(&mut self) -> &mut ProductionBucket {
+        if self.buckets.is_empty() {
+            self.buckets.push_back(ProductionBucket::default());
+        }
+        self.buckets.back_mut().unwrap()
+    }
+
+    /// items with something produced or consumed in the history, sorted by id
+    pub fn item_types(&self) -> BTreeSet<ItemType> {
+        self.buckets
+            .iter()
+            .flat_map(|bucket| bucket.produced.keys().chain(bucket.consumed.keys()))
+            .copied()
+            .collect()
+    }
+}
+
+/// items produced and consumed by the factory, per minute and per hour of game time
+#[derive(Resource, Debug, Clone)]
+pub struct ProductionStats {
+    pub minutes: ProductionHistory,
+    pub hours: ProductionHistory,
+}
+impl Default for ProductionStats {
+    fn default() -> Self {
+        Self {
+            minutes: ProductionHistory::new(ProductionPeriod::Minute, Self::MAX_BUCKETS),
+            hours: ProductionHistory::new(ProductionPeriod::Hour, Self::MAX_BUCKETS),
+        }
+    }
+}
+impl ProductionStats {
+    /// the last hour in minutes, the last 60 hours in hours
+    pub const MAX_BUCKETS: usize = 60;
+
+    pub fn history(&self, period: ProductionPeriod) -> &ProductionHistory {
+        match period {
+            ProductionPeriod::Minute => &self.minutes,
+            ProductionPeriod::Hour => &self.hours,
+        }
+    }
+
+    pub fn advance(&mut self, tick: u64) {
+        self.minutes.advance(tick);
+        self.hours.advance(tick);
+    }
+
+    pub fn record_produced(&mut self, item_stack: ItemStack) {
+        for history in [&mut self.minutes, &mut self.hours] {
+            *history
+                .current_bucket()
+                .produced
+                .entry(item_stack.item_type)
+                .or_default() += item_stack.quantity;
+        }
+    }
+
+    pub fn record_consumed(&mut self, item_stack: ItemStack) {
+        for history in [&mut self.minutes, &mut self.hours] {
+            *history
+                .current_bucket()
+                .consumed
+                .entry(item_stack.item_type)
+                .or_default() += item_stack.quantity;
+        }
+    }
+
+    /// one line per period, bucket and item, oldest first
+    pub fn to_csv(&self) -> String {
+        let mut csv = "period,start_tick,start_seconds,item,produced,consumed\n".to_owned();
+        for period in ProductionPeriod::ALL {
+            let history = self.history(period);
+            let item_types = history.item_types();
+            for bucket in &history.buckets {
+                for item_type in &item_types {
+                    let produced = bucket.produced.get(item_type).copied().unwrap_or(0);
+                    let consumed = bucket.consumed.get(item_type).copied().unwrap_or(0);
+                    if produced == 0 && consumed == 0 {
+                        continue;
+                    }
+                    let _ = writeln!(
+                        csv,
+                        "{:?},{},{},{},{},{}",
+                        period,
+                        bucket.start_tick,
+                        bucket.start_tick / GameTime::TICKS_PER_SECOND,
+                        item_type,
+                        produced,
+                        consumed
+                    );
+                }
+            }
+        }
+        csv
+    }
+}
+
+pub fn advance_production_stats_system(
+    game_time: Res<GameTime>,
+    mut production_stats: ResMut<ProductionStats>,
+) {
+    production_stats.advance(game_time.ticks);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::items::Quality;
+
+    #[test]
+    fn test_production_history_buckets() {
+        let minute = ProductionPeriod::Minute.bucket_ticks();
+        let mut production_stats = ProductionStats::default();
+        let ore = ItemStack::new(ItemType::IRON_ORE, Quality::Standard, 2);
+
+        production_stats.advance(0);
+        production_stats.record_produced(ore);
+        // two minutes later, the minute without production is an empty bucket
+        production_stats.advance(2 * minute + 5);
+        production_stats.record_produced(ore);
+        production_stats.record_consumed(ore);
+
+        let buckets = &production_stats.minutes.buckets;
+        assert_eq!(buckets.len(), 3);
+        assert_eq!(buckets[0].produced[&ItemType::IRON_ORE], 2);
+        assert!(buckets[1].produced.is_empty());
+        assert_eq!(buckets[2].start_tick, 2 * minute);
+        assert_eq!(buckets[2].consumed[&ItemType::IRON_ORE], 2);
+        // the same hour
+        assert_eq!(production_stats.hours.buckets.len(), 1);
+        assert_eq!(
+            production_stats.hours.buckets[0].produced[&ItemType::IRON_ORE],
+            4
+        );
+
+        // only the last buckets are kept
+        production_stats.advance(100 * minute);
+        assert_eq!(
+            production_stats.minutes.buckets.len(),
+            ProductionStats::MAX_BUCKETS
+        );
+        assert_eq!(
+            production_stats.minutes.buckets.back().unwrap().start_tick,
+            100 * minute
+        );
+
+        // game time going back starts over
+        production_stats.advance(0);
+        assert_eq!(production_stats.minutes.buckets.len(), 1);
+    }
+
+    #[test]
+    fn test_production_csv() {
+        let mut production_stats = ProductionStats::default();
+        production_stats.advance(0);
+        production_stats.record_produced(ItemStack::new(
+            ItemType::IRON_PLATE,
+            Quality::Standard,
+            3,
+        ));
+
+        assert_eq!(
+            production_stats.to_csv(),
+            "period,start_tick,start_seconds,item,produced,consumed\n\
+             Minute,0,0,iron_plate,3,0\n\
+             Hour,0,0,iron_plate,3,0\n"
+        );
+    }
+}
@@ -1,55 +1,360 @@
 use crate::{
-    items::{ItemType, Quality, inventory::ItemStack},
-    map::structure::machine::Machine,
+    items::{
+        ItemType, Quality, intern,
+        inventory::{Inventory, ItemStack},
+        registry::ItemRegistry,
+    },
+    loading::DefinitionsError,
+    map::structure::machine::{Machine, MachineKind},
 };
-use bevy::ecs::resource::Resource;
-use std::collections::HashMap;
+use bevy::{asset::AssetLoadFailedEvent, prelude::*};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{collections::HashMap, fmt};
 
-// #[derive(Debug, Clone, PartialEq, Eq)]
-// pub struct RecipeItemStack {
-//     pub item_type: ItemType,
-//     pub quantity: u32,
-// }
+pub const PATH_RECIPE_DEFINITIONS: &str = "recipes/base.recipes.json";
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct RecipeItemStack {
+    pub item_type: ItemType,
+    pub quantity: u32,
+    /// inputs: None accepts any quality, outputs: None takes the quality rolled by the machine
+    #[serde(default)]
+    pub quality: Option<Quality>,
+}
+impl RecipeItemStack {
+    pub fn to_item_stack(self, quality: Quality) -> ItemStack {
+        ItemStack::new(
+            self.item_type,
+            self.quality.unwrap_or(quality),
+            self.quantity,
+        )
+    }
+
+    pub fn is_available_in(&self, inventory: &Inventory) -> bool {
+        let count = match self.quality {
+            Some(quality) => inventory.count(self.item_type, quality),
+            None => inventory.count_any_quality(self.item_type),
+        };
+        count >= self.quantity
+    }
+
+    /// returns the item stacks taken, of one or several qualities
+    pub fn take_from(&self, inventory: &mut Inventory) -> Vec<ItemStack> {
+        match self.quality {
+            Some(quality) => vec![inventory.remove(self.to_item_stack(quality))],
+            None => inventory.remove_any_quality(self.item_type, self.quantity),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
 pub struct Recipe {
-    pub inputs: Vec<ItemStack>,
-    pub outputs: Vec<ItemStack>,
+    pub inputs: Vec<RecipeItemStack>,
+    pub outputs: Vec<RecipeItemStack>,
+    #[serde(default = "Recipe::default_craft_time_ticks")]
     pub base_craft_time_ticks: u64,
+    /// kinds of machine able to craft the recipe
+    pub machines: Vec<MachineKind>,
 }
 impl Recipe {
     const DEFAULT_CRAFT_TIME_TICKS: u64 = Machine::DEFAULT_ACTION_TIME_TICKS;
+
+    fn default_craft_time_ticks() -> u64 {
+        Self::DEFAULT_CRAFT_TIME_TICKS
+    }
+
+    pub fn inputs_available_in(&self, inventory: &Inventory) -> bool {
+        self.inputs
+            .iter()
+            .all(|input| input.is_available_in(inventory))
+    }
+}
+
+/// id of a recipe of the RecipeBook, recipes themselves are defined in assets/recipes/
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RecipeId(&'static str);
+impl RecipeId {
+    // recipes the code refers to directly, they must exist in the recipe definitions
+    pub const IRON_PLATE_TO_IRON_GEAR: Self = Self("iron_plate_to_iron_gear");
+    pub const COPPER_PLATE_TO_COPPER_WIRE: Self = Self("copper_plate_to_copper_wire");
+    pub const IRON_ORE_TO_IRON_PLATE: Self = Self("iron_ore_to_iron_plate");
+    pub const COPPER_ORE_TO_COPPER_PLATE: Self = Self("copper_ore_to_copper_plate");
+
+    pub fn new(id: &str) -> Self {
+        Self(intern(id))
+    }
+
+    pub fn id(&self) -> &'static str {
+        self.0
+    }
+}
+impl fmt::Debug for RecipeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+impl fmt::Display for RecipeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+impl Serialize for RecipeId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.0)
+    }
+}
+impl<'de> Deserialize<'de> for RecipeId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let id = String::deserialize(deserializer)?;
+        Ok(Self::new(&id))
+    }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub enum RecipeId {
-    IronPlateToIronGear,
-    CopperPlateToCopperWire,
+#[derive(Debug, Clone, Deserialize)]
+pub struct RecipeDefinition {
+    pub id: RecipeId,
+    #[serde(flatten)]
+    pub recipe: Recipe,
+}
+
+/// content of a *.recipes.json file
+#[derive(Asset, TypePath, Debug, Deserialize)]
+pub struct RecipeDefinitions {
+    pub recipes: Vec<RecipeDefinition>,
 }
 
 #[derive(Resource)]
+pub struct RecipeDefinitionsHandle(pub Handle<RecipeDefinitions>);
+
+#[derive(Debug)]
+pub enum RecipeError {
+    DuplicateId(RecipeId),
+    UnknownItem {
+        recipe_id: RecipeId,
+        item_type: ItemType,
+    },
+    ZeroQuantity {
+        recipe_id: RecipeId,
+        item_type: ItemType,
+    },
+    NoOutput(RecipeId),
+    NoMachine(RecipeId),
+}
+impl fmt::Display for RecipeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DuplicateId(recipe_id) => write!(f, "recipe {} is defined twice", recipe_id),
+            Self::UnknownItem {
+                recipe_id,
+                item_type,
+            } => write!(f, "recipe {} uses unknown item {}", recipe_id, item_type),
+            Self::ZeroQuantity {
+                recipe_id,
+                item_type,
+            } => write!(f, "recipe {} uses 0 {}", recipe_id, item_type),
+            Self::NoOutput(recipe_id) => write!(f, "recipe {} has no output", recipe_id),
+            Self::NoMachine(recipe_id) => {
+                write!(f, "recipe {} can't be crafted by any machine", recipe_id)
+            }
+        }
+    }
+}
+impl std::error::Error for RecipeError {}
+
+#[derive(Resource, Default)]
 pub struct RecipeBook(pub HashMap<RecipeId, Recipe>);
-impl Default for RecipeBook {
-    fn default() -> Self {
+impl RecipeBook {
+    pub fn from_definitions(
+        definitions: Vec<RecipeDefinition>,
+        item_registry: &ItemRegistry,
+    ) -> Result<Self, RecipeError> {
         let mut recipes = HashMap::new();
+        for RecipeDefinition { id, recipe } in definitions {
+            if recipe.outputs.is_empty() {
+                return Err(RecipeError::NoOutput(id));
+            }
+            if recipe.machines.is_empty() {
+                return Err(RecipeError::NoMachine(id));
+            }
+            for item_stack in recipe.inputs.iter().chain(&recipe.outputs) {
+                if !item_registry.contains(item_stack.item_type) {
+                    return Err(RecipeError::UnknownItem {
+                        recipe_id: id,
+                        item_type: item_stack.item_type,
+                    });
+                }
+                if item_stack.quantity == 0 {
+                    return Err(RecipeError::ZeroQuantity {
+                        recipe_id: id,
+                        item_type: item_stack.item_type,
+                    });
+                }
+            }
+            if recipes.insert(id, recipe).is_some() {
+                return Err(RecipeError::DuplicateId(id));
+            }
+        }
+        Ok(Self(recipes))
+    }
+
+    /// first recipe, by id, of the machine kind whose inputs are all in the inventory
+    pub fn recipe_for_inputs(&self, kind: MachineKind, inventory: &Inventory) -> Option<RecipeId> {
+        self.0
+            .iter()
+            .filter(|(_, recipe)| recipe.machines.contains(&kind))
+            .filter(|(_, recipe)| recipe.inputs_available_in(inventory))
+            .map(|(recipe_id, _)| *recipe_id)
+            .min()
+    }
+}
+
+pub fn load_recipe_definitions_system(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(RecipeDefinitionsHandle(
+        asset_server.load(PATH_RECIPE_DEFINITIONS),
+    ));
+}
+
+/// rebuilds the book each time the definitions file or the item registry change,
+/// a bad file stops the loading of the game or keeps the previous book when reloaded
+pub fn update_recipe_book_system(
+    mut asset_events: MessageReader<AssetEvent<RecipeDefinitions>>,
+    mut load_failed_events: MessageReader<AssetLoadFailedEvent<RecipeDefinitions>>,
+    recipe_definitions: Res<Assets<RecipeDefinitions>>,
+    handle: Res<RecipeDefinitionsHandle>,
+    item_registry: Res<ItemRegistry>,
+    mut recipe_book: ResMut<RecipeBook>,
+    mut definitions_errors: MessageWriter<DefinitionsError>,
+) {
+    for load_failed_event in load_failed_events.read() {
+        let message = format!(
+            "Couldn't load {}: {}",
+            load_failed_event.path, load_failed_event.error
+        );
+        error!("{}", message);
+        definitions_errors.write(DefinitionsError(message));
+    }
+    let definitions_changed = asset_events.read().any(|asset_event| {
+        asset_event.is_loaded_with_dependencies(&handle.0) || asset_event.is_modified(&handle.0)
+    });
+    if !definitions_changed && !item_registry.is_changed() {
+        return;
+    }
+    if item_registry.is_empty() {
+        return;
+    }
+    let Some(definitions) = recipe_definitions.get(&handle.0) else {
+        return;
+    };
+
+    match RecipeBook::from_definitions(definitions.recipes.clone(), &item_registry) {
+        Ok(book) => {
+            info!("Loaded {} recipes", book.0.len());
+            *recipe_book = book;
+        }
+        Err(error) => {
+            let message = format!("Invalid recipe definitions: {}", error);
+            error!("{}", message);
+            definitions_errors.write(DefinitionsError(message));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::items::registry::ItemDefinitions;
+
+    fn base_item_registry() -> ItemRegistry {
+        let definitions: ItemDefinitions =
+            serde_json::from_str(include_str!("../../assets/items/base.items.json")).unwrap();
+        ItemRegistry::from_definitions(definitions.items).unwrap()
+    }
+
+    fn parse_recipes(json: &str) -> Vec<RecipeDefinition> {
+        serde_json::from_str::<RecipeDefinitions>(json)
+            .unwrap()
+            .recipes
+    }
+
+    #[test]
+    fn test_base_recipe_definitions() {
+        let definitions = parse_recipes(include_str!("../../assets/recipes/base.recipes.json"));
+        let recipe_book = RecipeBook::from_definitions(definitions, &base_item_registry()).unwrap();
+
+        // the recipes used in code must be defined
+        assert!(
+            recipe_book
+                .0
+                .contains_key(&RecipeId::IRON_PLATE_TO_IRON_GEAR)
+        );
+        assert!(
+            recipe_book
+                .0
+                .contains_key(&RecipeId::COPPER_PLATE_TO_COPPER_WIRE)
+        );
+        assert!(
+            recipe_book
+                .0
+                .contains_key(&RecipeId::IRON_ORE_TO_IRON_PLATE)
+        );
+        assert!(
+            recipe_book
+                .0
+                .contains_key(&RecipeId::COPPER_ORE_TO_COPPER_PLATE)
+        );
+    }
+
+    #[test]
+    fn test_recipe_for_inputs() {
+        let item_registry = base_item_registry();
+        let definitions = parse_recipes(include_str!("../../assets/recipes/base.recipes.json"));
+        let recipe_book = RecipeBook::from_definitions(definitions, &item_registry).unwrap();
+
+        let mut inventory = Inventory::default();
+        assert_eq!(
+            recipe_book.recipe_for_inputs(MachineKind::Furnace, &inventory),
+            None
+        );
+        inventory.insert(
+            ItemStack::new(ItemType::IRON_ORE, Quality::Standard, 1),
+            &item_registry,
+        );
+        assert_eq!(
+            recipe_book.recipe_for_inputs(MachineKind::Furnace, &inventory),
+            Some(RecipeId::IRON_ORE_TO_IRON_PLATE)
+        );
+        // iron ore is not smelted by crafting machines
+        assert_eq!(
+            recipe_book.recipe_for_inputs(MachineKind::CraftingMachine, &inventory),
+            None
+        );
+    }
+
+    #[test]
+    fn test_invalid_recipe_definitions() {
+        let unknown_item = parse_recipes(
+            r#"{ "recipes": [{
+                "id": "ore_to_nothing",
+                "inputs": [{ "item_type": "iron_ore", "quantity": 1 }],
+                "outputs": [{ "item_type": "nothing", "quantity": 1 }],
+                "machines": ["CraftingMachine"]
+            }] }"#,
+        );
+        assert!(matches!(
+            RecipeBook::from_definitions(unknown_item, &base_item_registry()),
+            Err(RecipeError::UnknownItem { .. })
+        ));
 
-        recipes.insert(
-            RecipeId::IronPlateToIronGear,
-            Recipe {
-                inputs: vec![ItemStack {
-                    item_type: ItemType::IronPlate,
-                    quantity: 2,
-                    quality: Quality::Standard,
-                }],
-                outputs: vec![ItemStack {
-                    item_type: ItemType::IronGear,
-                    quantity: 1,
-                    quality: Quality::Standard,
-                }],
-                base_craft_time_ticks: Recipe::DEFAULT_CRAFT_TIME_TICKS,
-            },
-        );
-
-        RecipeBook(recipes)
+        let recipe = r#"{
+            "id": "ore_to_plate",
+            "inputs": [{ "item_type": "iron_ore", "quantity": 1 }],
+            "outputs": [{ "item_type": "iron_plate", "quantity": 1 }],
+            "machines": ["CraftingMachine"]
+        }"#;
+        let duplicate_id = parse_recipes(&format!(r#"{{ "recipes": [{}, {}] }}"#, recipe, recipe));
+        assert!(matches!(
+            RecipeBook::from_definitions(duplicate_id, &base_item_registry()),
+            Err(RecipeError::DuplicateId(_))
+        ));
     }
 }
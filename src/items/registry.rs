@@ -0,0 +1,310 @@
+use bevy::{asset::AssetLoadFailedEvent, prelude::*};
+use serde::Deserialize;
+use std::{collections::HashMap, fmt};
+
+use crate::{
+    items::{
+        ItemType,
+        inventory::DEFAULT_ITEM_STACK_LIMIT,
+        recipe::{
+            PATH_RECIPE_DEFINITIONS, RecipeBook, RecipeDefinitions, load_recipe_definitions_system,
+            update_recipe_book_system,
+        },
+    },
+    loading::{DefinitionsError, JsonAssetLoader},
+    map::structure::StructureKind,
+};
+
+pub const PATH_ITEM_DEFINITIONS: &str = "items/base.items.json";
+
+pub struct ItemsPlugin;
+impl Plugin for ItemsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<ItemDefinitions>()
+            .init_asset::<RecipeDefinitions>()
+            .register_asset_loader(JsonAssetLoader::<ItemDefinitions>::new(&["items.json"]))
+            .register_asset_loader(JsonAssetLoader::<RecipeDefinitions>::new(&["recipes.json"]))
+            .init_resource::<ItemRegistry>()
+            .init_resource::<RecipeBook>()
+            .add_systems(
+                Startup,
+                (load_item_definitions_system, load_recipe_definitions_system),
+            )
+            // also runs while loading: the game waits for the registry and the book to be filled
+            .add_systems(
+                Update,
+                (
+                    reload_definitions_on_key_system,
+                    update_item_registry_system,
+                    update_recipe_book_system,
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum ItemCategory {
+    RawResource,
+    Intermediate,
+    Component,
+    Building,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ItemDefinition {
+    pub id: ItemType,
+    /// name displayed to the player
+    pub name: String,
+    pub sprite: String,
+    pub stack_size: u32,
+    pub category: ItemCategory,
+    /// structure built when the player places the item
+    #[serde(default)]
+    pub places: Option<StructureKind>,
+    /// energy in kJ given by the item when it is burnt, None if it can't be burnt
+    #[serde(default)]
+    pub fuel_value: Option<f32>,
+}
+#[cfg(test)]
+impl ItemDefinition {
+    /// raw resource named after its id, the other fields are set with ..ItemDefinition::test(..)
+    pub fn test(id: ItemType, stack_size: u32) -> Self {
+        Self {
+            id,
+            name: id.to_string(),
+            sprite: "default.png".to_owned(),
+            stack_size,
+            category: ItemCategory::RawResource,
+            places: None,
+            fuel_value: None,
+        }
+    }
+}
+/// a registry with only coal, 100 kJ per item, and a fuel inventory holding coal_count of it
+#[cfg(test)]
+pub fn test_coal_fuel(coal_count: u32) -> (ItemRegistry, crate::items::inventory::FuelInventory) {
+    use crate::items::{
+        Quality,
+        inventory::{FuelInventory, Inventory, ItemStack},
+    };
+
+    let item_registry = ItemRegistry::from_definitions(vec![ItemDefinition {
+        fuel_value: Some(100.0),
+        ..ItemDefinition::test(ItemType::COAL, 50)
+    }])
+    .unwrap();
+    let fuel_inventory = FuelInventory(Inventory {
+        slots: vec![ItemStack::new(
+            ItemType::COAL,
+            Quality::Standard,
+            coal_count,
+        )],
+        ..default()
+    });
+    (item_registry, fuel_inventory)
+}
+
+/// content of an *.items.json file
+#[derive(Asset, TypePath, Debug, Deserialize)]
+pub struct ItemDefinitions {
+    pub items: Vec<ItemDefinition>,
+}
+
+#[derive(Resource)]
+pub struct ItemDefinitionsHandle(pub Handle<ItemDefinitions>);
+
+#[derive(Debug)]
+pub enum ItemDefinitionError {
+    DuplicateId(ItemType),
+    ZeroStackSize(ItemType),
+}
+impl fmt::Display for ItemDefinitionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DuplicateId(item_type) => write!(f, "item {} is defined twice", item_type),
+            Self::ZeroStackSize(item_type) => {
+                write!(f, "item {} has a stack size of 0", item_type)
+            }
+        }
+    }
+}
+impl std::error::Error for ItemDefinitionError {}
+
+/// every item the game knows about, in the order of the definitions file
+#[derive(Resource, Default)]
+pub struct ItemRegistry {
+    definitions: Vec<ItemDefinition>,
+    indices: HashMap<ItemType, usize>,
+}
+impl ItemRegistry {
+    pub fn from_definitions(definitions: Vec<ItemDefinition>) -> Result<Self, ItemDefinitionError> {
+        let mut indices = HashMap::new();
+        for (index, definition) in definitions.iter().enumerate() {
+            if definition.stack_size == 0 {
+                return Err(ItemDefinitionError::ZeroStackSize(definition.id));
+            }
+            if indices.insert(definition.id, index).is_some() {
+                return Err(ItemDefinitionError::DuplicateId(definition.id));
+            }
+        }
+        Ok(Self {
+            definitions,
+            indices,
+        })
+    }
+
+    pub fn get(&self, item_type: ItemType) -> Option<&ItemDefinition> {
+        self.indices
+            .get(&item_type)
+            .map(|index| &self.definitions[*index])
+    }
+
+    pub fn stack_size(&self, item_type: ItemType) -> u32 {
+        self.get(item_type)
+            .map_or(DEFAULT_ITEM_STACK_LIMIT, |definition| definition.stack_size)
+    }
+
+    /// display name, the id for the items missing from the registry
+    pub fn name(&self, item_type: ItemType) -> &str {
+        self.get(item_type)
+            .map_or(item_type.id(), |definition| definition.name.as_str())
+    }
+
+    /// the item that builds the structure
+    pub fn item_placing(&self, kind: StructureKind) -> Option<ItemType> {
+        self.definitions
+            .iter()
+            .find(|definition| definition.places == Some(kind))
+            .map(|definition| definition.id)
+    }
+
+    pub fn fuel_value(&self, item_type: ItemType) -> Option<f32> {
+        self.get(item_type)
+            .and_then(|definition| definition.fuel_value)
+    }
+
+    pub fn contains(&self, item_type: ItemType) -> bool {
+        self.indices.contains_key(&item_type)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ItemDefinition> {
+        self.definitions.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.definitions.is_empty()
+    }
+}
+
+pub fn load_item_definitions_system(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(ItemDefinitionsHandle(
+        asset_server.load(PATH_ITEM_DEFINITIONS),
+    ));
+}
+
+/// edited definitions are also reloaded automatically when bevy's file_watcher feature is enabled
+pub fn reload_definitions_on_key_system(
+    input: Res<ButtonInput<KeyCode>>,
+    asset_server: Res<AssetServer>,
+) {
+    if input.just_pressed(KeyCode::F8) {
+        info!("Reloading item and recipe definitions");
+        asset_server.reload(PATH_ITEM_DEFINITIONS);
+        asset_server.reload(PATH_RECIPE_DEFINITIONS);
+    }
+}
+
+/// rebuilds the registry each time the definitions file is (re)loaded,
+/// a bad file stops the loading of the game or keeps the previous registry when reloaded
+pub fn update_item_registry_system(
+    mut asset_events: MessageReader<AssetEvent<ItemDefinitions>>,
+    mut load_failed_events: MessageReader<AssetLoadFailedEvent<ItemDefinitions>>,
+    item_definitions: Res<Assets<ItemDefinitions>>,
+    handle: Res<ItemDefinitionsHandle>,
+    mut item_registry: ResMut<ItemRegistry>,
+    mut definitions_errors: MessageWriter<DefinitionsError>,
+) {
+    for load_failed_event in load_failed_events.read() {
+        let message = format!(
+            "Couldn't load {}: {}",
+            load_failed_event.path, load_failed_event.error
+        );
+        error!("{}", message);
+        definitions_errors.write(DefinitionsError(message));
+    }
+    for asset_event in asset_events.read() {
+        if !asset_event.is_loaded_with_dependencies(&handle.0)
+            && !asset_event.is_modified(&handle.0)
+        {
+            continue;
+        }
+        let Some(definitions) = item_definitions.get(&handle.0) else {
+            continue;
+        };
+
+        match ItemRegistry::from_definitions(definitions.items.clone()) {
+            Ok(registry) => {
+                info!("Loaded {} item definitions", registry.definitions.len());
+                *item_registry = registry;
+            }
+            Err(error) => {
+                let message = format!("Invalid item definitions: {}", error);
+                error!("{}", message);
+                definitions_errors.write(DefinitionsError(message));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base_item_definitions() {
+        let definitions: ItemDefinitions =
+            serde_json::from_str(include_str!("../../assets/items/base.items.json")).unwrap();
+        let item_registry = ItemRegistry::from_definitions(definitions.items).unwrap();
+
+        // the items used in code must be defined
+        for item_type in [
+            ItemType::IRON_ORE,
+            ItemType::COPPER_ORE,
+            ItemType::COAL,
+            ItemType::IRON_PLATE,
+            ItemType::COPPER_PLATE,
+            ItemType::IRON_GEAR,
+            ItemType::COPPER_WIRE,
+            ItemType::WALL,
+            ItemType::BELT,
+            ItemType::INSERTER,
+            ItemType::CHEST,
+            ItemType::CRAFTING_MACHINE,
+            ItemType::MINING_MACHINE,
+            ItemType::BURNER_CRAFTING_MACHINE,
+            ItemType::BURNER_MINING_MACHINE,
+            ItemType::FURNACE,
+            ItemType::POWER_POLE,
+            ItemType::SOLAR_PANEL,
+            ItemType::BURNER_GENERATOR,
+        ] {
+            assert!(
+                item_registry.contains(item_type),
+                "{} is missing",
+                item_type
+            );
+        }
+        // ids read from a file are the same as the constants
+        assert_eq!(ItemType::new("iron_ore"), ItemType::IRON_ORE);
+    }
+
+    #[test]
+    fn test_duplicate_item_definition() {
+        let definition = ItemDefinition::test(ItemType::IRON_ORE, 10);
+        assert!(matches!(
+            ItemRegistry::from_definitions(vec![definition.clone(), definition]),
+            Err(ItemDefinitionError::DuplicateId(ItemType::IRON_ORE))
+        ));
+    }
+}
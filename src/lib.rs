@@ -3,11 +3,12 @@ use bevy::ecs::schedule::SystemSet;
 pub mod camera;
 pub mod direction;
 pub mod items;
+pub mod loading;
 pub mod map;
 pub mod physics;
-// pub mod save;
-pub mod loading;
+pub mod save;
 pub mod time;
+pub mod ui;
 pub mod units;
 
 #[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
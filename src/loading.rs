@@ -1,24 +1,40 @@
+use bevy::asset::{AssetLoader, LoadContext, io::Reader};
 use bevy::prelude::*;
 use bevy::tasks::AsyncComputeTaskPool;
 use event_listener::Event;
 use futures_lite::Future;
+use serde::de::DeserializeOwned;
+use std::marker::PhantomData;
 use std::sync::{
     Arc,
     atomic::{AtomicBool, AtomicU32, Ordering},
 };
+
+use crate::items::{recipe::RecipeBook, registry::ItemRegistry};
 pub struct LoadingPlugin;
 
 impl Plugin for LoadingPlugin {
     fn build(&self, app: &mut App) {
         app.init_state::<LoadingState>()
+            .add_message::<DefinitionsError>()
             .add_systems(Startup, setup_loading)
             .add_systems(
                 Update,
-                (convert_tileset_to_array, check_loading_complete)
+                (
+                    convert_tileset_to_array,
+                    fail_loading_on_definitions_error,
+                    check_loading_complete,
+                )
                     .chain()
                     .run_if(in_state(LoadingState::Loading)),
             )
-            .add_systems(OnExit(LoadingState::Loading), cleanup_loading);
+            .add_systems(
+                OnTransition {
+                    exited: LoadingState::Loading,
+                    entered: LoadingState::Ready,
+                },
+                cleanup_loading,
+            );
     }
 }
 
@@ -62,8 +78,14 @@ pub enum LoadingState {
     #[default]
     Loading,
     Ready,
+    /// the game can't start, the loading text tells why
+    Failed,
 }
 
+/// an items or recipes file that couldn't be loaded or is invalid, only fatal while loading
+#[derive(Message)]
+pub struct DefinitionsError(pub String);
+
 #[derive(Component)]
 pub struct LoadingEntity;
 
@@ -164,15 +186,35 @@ fn convert_tileset_to_array(
     }
 }
 
+/// without valid definitions the game would wait forever, the error replaces the loading text
+fn fail_loading_on_definitions_error(
+    mut definitions_errors: MessageReader<DefinitionsError>,
+    mut text_query: Query<&mut Text, With<LoadingEntity>>,
+    mut next_state: ResMut<NextState<LoadingState>>,
+) {
+    let Some(definitions_error) = definitions_errors.read().last() else {
+        return;
+    };
+    for mut text in text_query.iter_mut() {
+        text.0 = format!("Loading failed: {}", definitions_error.0);
+    }
+    next_state.set(LoadingState::Failed);
+}
+
 fn check_loading_complete(
     async_state: Res<AsyncLoadingState>,
     mut next_state: ResMut<NextState<LoadingState>>,
     tileset_handle: Res<TilesetHandle>,
     images: Res<Assets<Image>>,
+    item_registry: Res<ItemRegistry>,
+    recipe_book: Res<RecipeBook>,
 ) {
     // Vérifie que l'image est chargée (pas besoin de vérifier la conversion)
     if async_state.0.load(Ordering::Acquire) {
-        if images.get(&tileset_handle.0).is_some() {
+        if images.get(&tileset_handle.0).is_some()
+            && !item_registry.is_empty()
+            && !recipe_book.0.is_empty()
+        {
             println!("Loading complete!");
             next_state.set(LoadingState::Ready);
         }
@@ -188,3 +230,37 @@ fn cleanup_loading(mut commands: Commands, loading_entities: Query<Entity, With<
     commands.remove_resource::<AsyncLoadingState>();
     // On garde TilesetHandle pour pouvoir l'utiliser plus tard si nécessaire
 }
+
+/// loads any asset that can be deserialized from a json file, like the item definitions
+pub struct JsonAssetLoader<A> {
+    extensions: &'static [&'static str],
+    _asset: PhantomData<fn() -> A>,
+}
+impl<A> JsonAssetLoader<A> {
+    pub fn new(extensions: &'static [&'static str]) -> Self {
+        Self {
+            extensions,
+            _asset: PhantomData,
+        }
+    }
+}
+impl<A: Asset + DeserializeOwned> AssetLoader for JsonAssetLoader<A> {
+    type Asset = A;
+    type Settings = ();
+    type Error = BevyError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<A, BevyError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        self.extensions
+    }
+}
@@ -5,7 +5,12 @@ use overlord::{
         CameraMovement, CameraMovementKind, DayNightOverlay, handle_camera_inputs_system,
         update_map_visibility_camera_change_map_system,
     },
-    items::recipe::RecipeBook,
+    items::{
+        ItemType, Quality,
+        inventory::ItemStack,
+        production::ProductionPlugin,
+        registry::{ItemRegistry, ItemsPlugin},
+    },
     loading::{LoadingPlugin, LoadingState},
     map::{
         self, CurrentMapId, MapManager, MapPlugin, MultiMapManager,
@@ -13,11 +18,16 @@ use overlord::{
         spawn_first_chunk_system,
     },
     physics::{PhysicsPlugin, movement::SpeedStat},
+    save::SavePlugin,
     time::{
         GameTime, UpsCounter, day_night_cycle_system, display_fps_ups_system,
         fixed_update_counter_system,
     },
-    units::{Player, PlayerBundle, Unit, UnitBundle, pathfinding::PathfindingPlugin},
+    ui::UiPlugin,
+    units::{
+        Player, PlayerBundle, Unit, UnitBundle, build::BuildPlugin, mining::MiningPlugin,
+        pathfinding::PathfindingPlugin,
+    },
 };
 
 fn main() {
@@ -41,14 +51,18 @@ fn main() {
         )
         .add_plugins(LoadingPlugin)
         .add_plugins(FrameTimeDiagnosticsPlugin::default())
+        .add_plugins(ItemsPlugin)
+        .add_plugins(ProductionPlugin)
         .add_plugins(PhysicsPlugin)
         .add_plugins(PathfindingPlugin)
         .add_plugins(MapPlugin)
-        // .add_plugins(SavePlugin)
+        .add_plugins(SavePlugin)
+        .add_plugins(BuildPlugin)
+        .add_plugins(MiningPlugin)
+        .add_plugins(UiPlugin)
         // .insert_resource(TimeState::default())
         .insert_resource(GameTime::default())
         .insert_resource(UpsCounter::default())
-        .insert_resource(RecipeBook::default())
         .insert_resource(Time::<Fixed>::from_hz(GameTime::UPS_TARGET as f64))
         //.add_systems(Startup, setup_system.run_if(in_state(LoadingState::Ready)))
         .add_systems(
@@ -80,6 +94,7 @@ fn setup_system(
     asset_server: Res<AssetServer>,
     mut game_time: ResMut<GameTime>,
     mut multi_map_manager: ResMut<MultiMapManager>,
+    item_registry: Res<ItemRegistry>,
 ) {
     // Audio
     commands.spawn((
@@ -128,7 +143,26 @@ fn setup_system(
         CurrentMapId(map::DEFAULT_MAP_ID),
         SpeedStat::from_tiles_per_second(Unit::DEFAULT_TILE_PER_SECOND_SPEED),
     );
-    let bundle = PlayerBundle::new(unit_bundle);
+    let mut bundle = PlayerBundle::new(unit_bundle);
+    // buildings to start with
+    for (item_type, quantity) in [
+        (ItemType::BELT, 50),
+        (ItemType::INSERTER, 10),
+        (ItemType::CHEST, 5),
+        (ItemType::CRAFTING_MACHINE, 5),
+        (ItemType::MINING_MACHINE, 5),
+        (ItemType::BURNER_MINING_MACHINE, 5),
+        (ItemType::FURNACE, 5),
+        (ItemType::COAL, 20),
+        (ItemType::POWER_POLE, 20),
+        (ItemType::SOLAR_PANEL, 10),
+        (ItemType::BURNER_GENERATOR, 2),
+    ] {
+        bundle.inventory.insert(
+            ItemStack::new(item_type, Quality::Standard, quantity),
+            &item_registry,
+        );
+    }
     commands.spawn((
         bundle,
         Sprite::from_image(asset_server.load(Player::PATH_PNG).clone()),
@@ -1,4 +1,5 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::map::{CHUNK_SIZE, TILE_SIZE};
 
@@ -36,13 +37,13 @@ impl From<AbsoluteCoordinates> for Vec2 {
 }
 
 /// absolute_coord = (5.5 * TILE_SIZE.X, 0.5 * TILE_SIZE.y) | coord = (5.5, 0.5) | tile_coord = (5, 0)
-#[derive(Default, Debug, Hash, Clone, Copy, PartialEq, Eq)]
+#[derive(Default, Debug, Hash, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TileCoordinates {
     pub x: i32,
     pub y: i32,
 }
 
-#[derive(Default, Debug, Hash, Clone, Copy, PartialEq, Eq)]
+#[derive(Default, Debug, Hash, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct LocalTileCoordinates {
     pub x: i32,
     pub y: i32,
@@ -50,7 +51,7 @@ pub struct LocalTileCoordinates {
 
 /// chunk_coord : (1,1) is 1 right and 1 down
 /// Chunkcoord {x: 2, y: 2} <=> TileCoordinates {x: 2*CHUNK_SIZE, y: 2*CHUNK_SIZE}
-#[derive(Default, Debug, Hash, Clone, Copy, PartialEq, Eq)]
+#[derive(Default, Debug, Hash, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ChunkCoordinates {
     pub x: i32,
     pub y: i32,
@@ -135,6 +136,20 @@ pub fn absolute_coord_to_tile_coord(absolute_coord: AbsoluteCoordinates) -> Tile
     }
 }
 
+/// tile under the cursor, None if the cursor is outside of the window
+pub fn cursor_tile(
+    window: &Window,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+) -> Option<TileCoordinates> {
+    let cursor = window.cursor_position()?;
+    let world_position = camera.viewport_to_world_2d(camera_transform, cursor).ok()?;
+    Some(absolute_coord_to_tile_coord(AbsoluteCoordinates {
+        x: world_position.x,
+        y: world_position.y,
+    }))
+}
+
 /// Convertit une coordition monde (pixels) en coordition de chunk.
 pub fn absolute_coord_to_chunk_coord(absolute_coord: AbsoluteCoordinates) -> ChunkCoordinates {
     ChunkCoordinates {
@@ -1,10 +1,11 @@
 use bevy::{prelude::*, sprite_render::TilemapChunkTileData};
+use serde::{Deserialize, Serialize};
 
 use crate::map::{
     CHUNK_SIZE, ResourceNodeLayerManager, StructureLayerManager, coordinates::LocalTileCoordinates,
 };
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum FogState {
     /// black
     Unknown,
@@ -0,0 +1,388 @@
+use bevy::prelude::*;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use std::collections::HashMap;
+
+use crate::{
+    items::ItemType,
+    map::{
+        CHUNK_SIZE, MapId,
+        coordinates::{ChunkCoordinates, LocalTileCoordinates, local_tile_coord_to_tile_coord},
+    },
+};
+
+/// seed of the whole world; every chunk rng is derived from it so the same seed always gives the same chunks
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorldSeed(pub u64);
+impl WorldSeed {
+    pub fn random() -> Self {
+        Self(rand::random())
+    }
+
+    /// rng only depending on the seed, the map and the chunk coordinates
+    pub fn chunk_rng(&self, map_id: MapId, chunk_coord: ChunkCoordinates) -> StdRng {
+        let mut hash = splitmix64(self.0);
+        hash = splitmix64(hash ^ map_id.0 as u64);
+        hash = splitmix64(hash ^ chunk_coord.x as u32 as u64);
+        hash = splitmix64(hash ^ chunk_coord.y as u32 as u64);
+        StdRng::seed_from_u64(hash)
+    }
+
+    /// seed of one noise layer of a map; layers must differ so walls and ores don't follow the same shapes
+    pub fn layer_seed(&self, map_id: MapId, layer: u64) -> u64 {
+        splitmix64(splitmix64(self.0 ^ splitmix64(map_id.0 as u64)) ^ layer)
+    }
+}
+impl Default for WorldSeed {
+    fn default() -> Self {
+        Self::random()
+    }
+}
+
+/// mixes the bits of a u64 so close inputs (like neighbor chunks) give unrelated outputs
+pub fn splitmix64(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// index of the ground tiles in textures/array_texture.png
+pub mod ground {
+    pub const GRASS: u32 = 0;
+    pub const DIRT: u32 = 1;
+    pub const SNOW: u32 = 2;
+    pub const SAND: u32 = 3;
+}
+
+/// what the generator decided for one tile; spawn_one_chunk turns it into entities
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GeneratedTile {
+    /// index in the tileset
+    pub ground: u32,
+    pub is_wall: bool,
+    pub resource: Option<ItemType>,
+    /// items the resource node holds, 0 without resource
+    pub resource_amount: u32,
+}
+
+/// CHUNK_SIZE.x * CHUNK_SIZE.y tiles, same order as the tilemap (x + y * CHUNK_SIZE.x)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeneratedChunk {
+    pub tiles: Vec<GeneratedTile>,
+}
+impl GeneratedChunk {
+    pub fn get(&self, local: LocalTileCoordinates) -> GeneratedTile {
+        self.tiles[(local.x + local.y * CHUNK_SIZE.x as i32) as usize]
+    }
+}
+
+pub trait ChunkGenerator: Send + Sync {
+    /// must only depend on its parameters so a chunk can be generated again identically
+    fn generate(
+        &self,
+        world_seed: &WorldSeed,
+        map_id: MapId,
+        chunk_coord: ChunkCoordinates,
+    ) -> GeneratedChunk;
+}
+
+/// generator used for each map; maps without a specific generator use the default one
+#[derive(Resource)]
+pub struct ChunkGenerators {
+    default: Box<dyn ChunkGenerator>,
+    per_map: HashMap<MapId, Box<dyn ChunkGenerator>>,
+}
+impl ChunkGenerators {
+    pub fn new(default: impl ChunkGenerator + 'static) -> Self {
+        Self {
+            default: Box::new(default),
+            per_map: HashMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, map_id: MapId, generator: impl ChunkGenerator + 'static) {
+        self.per_map.insert(map_id, Box::new(generator));
+    }
+
+    pub fn get(&self, map_id: MapId) -> &dyn ChunkGenerator {
+        self.per_map
+            .get(&map_id)
+            .map(|generator| generator.as_ref())
+            .unwrap_or(self.default.as_ref())
+    }
+}
+impl Default for ChunkGenerators {
+    fn default() -> Self {
+        let mut generators = Self::new(NoiseChunkGenerator::plains());
+        generators.insert(MapId(1), NoiseChunkGenerator::caves());
+        generators
+    }
+}
+
+/// coherent noise generator: contiguous cave walls, biomes for the ground and ore veins
+#[derive(Debug, Clone)]
+pub struct NoiseChunkGenerator {
+    /// size in tiles of the cave shapes
+    pub cave_scale: f32,
+    /// cave noise above this is a wall
+    pub wall_threshold: f32,
+    /// size in tiles of the biomes
+    pub biome_scale: f32,
+    /// size in tiles of the ore veins
+    pub ore_scale: f32,
+    /// ore noise above this is an ore vein
+    pub ore_threshold: f32,
+    /// ore kind noise above this gives copper instead of iron
+    pub copper_threshold: f32,
+    /// ore kind noise below this gives coal instead of iron
+    pub coal_threshold: f32,
+    /// items of a node on the edge of a vein, the middle of the vein is up to 4 times richer
+    pub ore_amount: u32,
+}
+impl NoiseChunkGenerator {
+    const CAVE_LAYER: u64 = 1;
+    const BIOME_LAYER: u64 = 2;
+    const ORE_LAYER: u64 = 3;
+    const ORE_KIND_LAYER: u64 = 4;
+    /// tiles this close to a wall get the dirt ground of the caves
+    const CAVE_FLOOR_MARGIN: f32 = 0.05;
+
+    /// mostly open land with a few rock formations
+    pub fn plains() -> Self {
+        Self {
+            cave_scale: 24.0,
+            wall_threshold: 0.68,
+            biome_scale: 64.0,
+            ore_scale: 10.0,
+            ore_threshold: 0.72,
+            copper_threshold: 0.6,
+            coal_threshold: 0.3,
+            ore_amount: 200,
+        }
+    }
+
+    /// underground map: walls everywhere except tunnels, richer in ores
+    pub fn caves() -> Self {
+        Self {
+            cave_scale: 12.0,
+            wall_threshold: 0.5,
+            biome_scale: 64.0,
+            ore_scale: 8.0,
+            ore_threshold: 0.65,
+            copper_threshold: 0.5,
+            coal_threshold: 0.25,
+            ore_amount: 400,
+        }
+    }
+
+    fn ground(&self, cave: f32, biome: f32) -> u32 {
+        if cave > self.wall_threshold - Self::CAVE_FLOOR_MARGIN {
+            ground::DIRT
+        } else if biome < 0.35 {
+            ground::SAND
+        } else if biome < 0.65 {
+            ground::GRASS
+        } else {
+            ground::SNOW
+        }
+    }
+}
+impl ChunkGenerator for NoiseChunkGenerator {
+    fn generate(
+        &self,
+        world_seed: &WorldSeed,
+        map_id: MapId,
+        chunk_coord: ChunkCoordinates,
+    ) -> GeneratedChunk {
+        let cave_seed = world_seed.layer_seed(map_id, Self::CAVE_LAYER);
+        let biome_seed = world_seed.layer_seed(map_id, Self::BIOME_LAYER);
+        let ore_seed = world_seed.layer_seed(map_id, Self::ORE_LAYER);
+        let ore_kind_seed = world_seed.layer_seed(map_id, Self::ORE_KIND_LAYER);
+        // only used to make veins patchy; noise is sampled on tile coordinates so chunks connect
+        let mut rng = world_seed.chunk_rng(map_id, chunk_coord);
+
+        let mut tiles = Vec::with_capacity(CHUNK_SIZE.element_product() as usize);
+        for y in 0..CHUNK_SIZE.y as i32 {
+            for x in 0..CHUNK_SIZE.x as i32 {
+                let tile_coord =
+                    local_tile_coord_to_tile_coord(LocalTileCoordinates { x, y }, chunk_coord);
+                let (tx, ty) = (tile_coord.x as f32, tile_coord.y as f32);
+
+                let cave = fractal_noise(cave_seed, tx / self.cave_scale, ty / self.cave_scale, 4);
+                let biome =
+                    fractal_noise(biome_seed, tx / self.biome_scale, ty / self.biome_scale, 3);
+                let is_wall = cave > self.wall_threshold;
+
+                let mut resource = None;
+                let mut resource_amount = 0;
+                let ore = fractal_noise(ore_seed, tx / self.ore_scale, ty / self.ore_scale, 2);
+                if !is_wall && ore > self.ore_threshold {
+                    // denser in the middle of the vein
+                    let density = (ore - self.ore_threshold) / (1.0 - self.ore_threshold);
+                    if rng.random_bool((0.4 + density as f64).min(1.0)) {
+                        let ore_kind = value_noise(
+                            ore_kind_seed,
+                            tx / (self.ore_scale * 4.0),
+                            ty / (self.ore_scale * 4.0),
+                        );
+                        resource = Some(if ore_kind > self.copper_threshold {
+                            ItemType::COPPER_ORE
+                        } else if ore_kind < self.coal_threshold {
+                            ItemType::COAL
+                        } else {
+                            ItemType::IRON_ORE
+                        });
+                        resource_amount = (self.ore_amount as f32 * (1.0 + 3.0 * density)) as u32;
+                    }
+                }
+
+                tiles.push(GeneratedTile {
+                    ground: self.ground(cave, biome),
+                    is_wall,
+                    resource,
+                    resource_amount,
+                });
+            }
+        }
+
+        GeneratedChunk { tiles }
+    }
+}
+
+/// pseudo random value in [0, 1] for a lattice point
+fn lattice_value(seed: u64, x: i32, y: i32) -> f32 {
+    let hash = splitmix64(seed ^ splitmix64((x as u32 as u64) | ((y as u32 as u64) << 32)));
+    (hash >> 40) as f32 / (1u64 << 24) as f32
+}
+
+/// smooth value noise in [0, 1]; one lattice cell per unit
+pub fn value_noise(seed: u64, x: f32, y: f32) -> f32 {
+    let x0 = x.floor();
+    let y0 = y.floor();
+    let (xi, yi) = (x0 as i32, y0 as i32);
+    // smoothstep so there are no visible creases on the lattice
+    let tx = (x - x0) * (x - x0) * (3.0 - 2.0 * (x - x0));
+    let ty = (y - y0) * (y - y0) * (3.0 - 2.0 * (y - y0));
+
+    let top = lerp(
+        lattice_value(seed, xi, yi),
+        lattice_value(seed, xi + 1, yi),
+        tx,
+    );
+    let bottom = lerp(
+        lattice_value(seed, xi, yi + 1),
+        lattice_value(seed, xi + 1, yi + 1),
+        tx,
+    );
+    lerp(top, bottom, ty)
+}
+
+/// sum of octaves of value noise, normalized back to [0, 1]
+pub fn fractal_noise(seed: u64, x: f32, y: f32, octaves: u32) -> f32 {
+    let mut total = 0.0;
+    let mut amplitude = 1.0;
+    let mut frequency = 1.0;
+    let mut max_total = 0.0;
+    for octave in 0..octaves {
+        total += value_noise(
+            splitmix64(seed ^ octave as u64),
+            x * frequency,
+            y * frequency,
+        ) * amplitude;
+        max_total += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+    total / max_total
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    fn sample(rng: &mut StdRng) -> Vec<u32> {
+        (0..16).map(|_| rng.random()).collect()
+    }
+
+    #[test]
+    fn test_chunk_rng_is_deterministic() {
+        let seed = WorldSeed(42);
+        let chunk_coord = ChunkCoordinates { x: -3, y: 7 };
+        let first = sample(&mut seed.chunk_rng(MapId(0), chunk_coord));
+        let second = sample(&mut seed.chunk_rng(MapId(0), chunk_coord));
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_chunk_rng_depends_on_seed_map_and_chunk() {
+        let seed = WorldSeed(42);
+        let chunk_coord = ChunkCoordinates { x: 0, y: 0 };
+        let reference = sample(&mut seed.chunk_rng(MapId(0), chunk_coord));
+
+        let other_seed = sample(&mut WorldSeed(43).chunk_rng(MapId(0), chunk_coord));
+        assert_ne!(reference, other_seed);
+
+        let other_map = sample(&mut seed.chunk_rng(MapId(1), chunk_coord));
+        assert_ne!(reference, other_map);
+
+        let other_chunk = sample(&mut seed.chunk_rng(MapId(0), ChunkCoordinates { x: 1, y: 0 }));
+        assert_ne!(reference, other_chunk);
+
+        // swapping x and y must not give the same chunk
+        let a = sample(&mut seed.chunk_rng(MapId(0), ChunkCoordinates { x: 1, y: 2 }));
+        let b = sample(&mut seed.chunk_rng(MapId(0), ChunkCoordinates { x: 2, y: 1 }));
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn test_value_noise_is_continuous_and_in_range() {
+        let mut previous = value_noise(7, -20.0, 3.3);
+        for i in 1..4000 {
+            let x = -20.0 + i as f32 * 0.01;
+            let value = value_noise(7, x, 3.3);
+            assert!((0.0..=1.0).contains(&value));
+            assert!((value - previous).abs() < 0.05);
+            previous = value;
+        }
+    }
+
+    #[test]
+    fn test_noise_generator_is_deterministic() {
+        let generator = NoiseChunkGenerator::plains();
+        let chunk_coord = ChunkCoordinates { x: 2, y: -1 };
+        let first = generator.generate(&WorldSeed(1), MapId(0), chunk_coord);
+        let second = generator.generate(&WorldSeed(1), MapId(0), chunk_coord);
+        assert_eq!(first, second);
+        assert_eq!(first.tiles.len(), CHUNK_SIZE.element_product() as usize);
+
+        let other_seed = generator.generate(&WorldSeed(2), MapId(0), chunk_coord);
+        assert_ne!(first, other_seed);
+    }
+
+    #[test]
+    fn test_noise_generator_has_no_resource_on_walls() {
+        let generator = NoiseChunkGenerator::caves();
+        for x in -2..2 {
+            let chunk = generator.generate(&WorldSeed(3), MapId(1), ChunkCoordinates { x, y: 0 });
+            assert!(chunk.tiles.iter().any(|tile| tile.is_wall));
+            assert!(chunk.tiles.iter().any(|tile| !tile.is_wall));
+            assert!(
+                chunk
+                    .tiles
+                    .iter()
+                    .all(|tile| !(tile.is_wall && tile.resource.is_some()))
+            );
+            assert!(
+                chunk
+                    .tiles
+                    .iter()
+                    .all(|tile| tile.resource.is_some() == (tile.resource_amount > 0))
+            );
+        }
+    }
+}
@@ -3,53 +3,65 @@ use crate::{
     direction::Direction,
     items::{
         ItemType, Quality,
-        inventory::{InputInventory, ItemStack, OutputInventory},
+        inventory::{Inventory, ItemStack},
         recipe::RecipeId,
     },
     loading::LoadingState,
     map::{
         coordinates::{
-            ChunkCoordinates, GridPosition, LocalTileCoordinates, TileCoordinates,
-            absolute_coord_to_chunk_coord, local_tile_coord_to_tile_coord,
-            tile_coord_to_absolute_coord, tile_coord_to_chunk_coord,
-            tile_coord_to_local_tile_coord,
+            ChunkCoordinates, LocalTileCoordinates, TileCoordinates, absolute_coord_to_chunk_coord,
+            tile_coord_to_chunk_coord, tile_coord_to_local_tile_coord,
         },
         fog::ChunkFogOfWar,
+        generation::{ChunkGenerators, WorldSeed},
+        persistence::{
+            ChunkSave, MachineSave, StructureSave, clear_chunk_cache_system, read_chunk,
+            unload_far_chunks_system,
+        },
         resource_node::ResourceNode,
         structure::{
-            BlockSight, Structure, StructureBundle, WallBundle,
-            machine::{
-                BeltMachine, BeltMachineBundle, CraftingMachine, CraftingMachineBundle, Machine,
-                MachineBaseBundle, MachinePlugin, MiningMachine, MiningMachineBundle,
-            },
-            portal::PortalBundle,
+            BlockSight, Structure,
+            belt::{Belt, BeltPlugin},
+            chest::Chest,
+            inserter::{Inserter, InserterPlugin},
+            machine::{CraftingMachine, MachinePlugin, MiningMachine},
+            portal::Portal,
+            power::PowerPlugin,
         },
     },
-    physics::{collision_event::CollisionEffectCooldown, movement::Passable},
+    physics::movement::Passable,
     units::{Unit, pathfinding::RecalculateFlowField},
 };
 use bevy::{
     prelude::*,
-    sprite_render::{TileData, TilemapChunk, TilemapChunkTileData},
+    sprite_render::{TilemapChunk, TilemapChunkTileData},
 };
-use rand::Rng;
+use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, hash::Hash};
 
 pub const TILE_SIZE: UVec2 = UVec2 { x: 16, y: 16 };
 pub const CHUNK_SIZE: UVec2 = UVec2 { x: 32, y: 32 };
 pub const TILE_LAYER: f32 = -1.0;
 pub const DEFAULT_MAP_ID: MapId = MapId(0);
+/// chunks around each unit that are spawned
+pub const CHUNK_SPAWN_RADIUS: i32 = 2;
+/// chunks further than that from every unit are unloaded; bigger than CHUNK_SPAWN_RADIUS so chunks don't flicker at the border
+pub const CHUNK_UNLOAD_RADIUS: i32 = CHUNK_SPAWN_RADIUS + 2;
 
 pub struct MapPlugin;
 impl Plugin for MapPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(MachinePlugin)
+        app.add_plugins((MachinePlugin, BeltPlugin, InserterPlugin, PowerPlugin))
             .insert_resource(MultiMapManager::default())
+            .init_resource::<WorldSeed>()
+            .init_resource::<ChunkGenerators>()
+            .add_systems(Startup, clear_chunk_cache_system)
             .add_systems(
                 FixedUpdate,
                 (
                     // spawn_chunks_around_camera_system,
                     spawn_chunks_around_units_system,
+                    unload_far_chunks_system,
                 )
                     .chain()
                     .in_set(FixedSet::Process)
@@ -70,7 +82,7 @@ pub struct ResourceNodeLayerManager {
     pub sources: HashMap<LocalTileCoordinates, Entity>,
 }
 
-#[derive(Default, Debug, Hash, PartialEq, Eq, Clone, Copy)]
+#[derive(Default, Debug, Hash, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
 pub struct MapId(pub u32);
 
 #[derive(Component, Default, Debug, Clone, Copy)]
@@ -138,6 +150,7 @@ impl ChunkBundle {
 pub struct MapRoot(pub MapId);
 
 pub struct MapManager {
+    pub map_id: MapId,
     /// MapRoot; all chunks of the map are children of this entity; usefull to change visibility or despawn
     root_entity: Entity,
     pub chunks: HashMap<ChunkCoordinates, Entity>,
@@ -148,11 +161,17 @@ impl MapManager {
             .spawn((Transform::default(), Visibility::Hidden, MapRoot(map_id)))
             .id();
         Self {
+            map_id,
             root_entity,
             chunks: HashMap::default(),
         }
     }
 
+    /// despawns the MapRoot and so every chunk, structure and resource node of the map
+    pub fn despawn(self, commands: &mut Commands) {
+        commands.entity(self.root_entity).despawn();
+    }
+
     pub fn get_structure(
         &self,
         tile: TileCoordinates,
@@ -168,11 +187,25 @@ impl MapManager {
         None
     }
 
-    // TODO: try to load from save before spawning a new chunk
+    pub fn get_resource_node(
+        &self,
+        tile: TileCoordinates,
+        chunk_query: &Query<&ResourceNodeLayerManager, With<TilemapChunk>>,
+    ) -> Option<Entity> {
+        let chunk_coord = tile_coord_to_chunk_coord(tile);
+        let chunk_entity = self.chunks.get(&chunk_coord)?;
+        let resource_node_manager = chunk_query.get(*chunk_entity).ok()?;
+        let local_tile = tile_coord_to_local_tile_coord(tile, chunk_coord);
+        resource_node_manager.sources.get(&local_tile).copied()
+    }
+
+    /// the chunk is loaded from disk if it has been unloaded before
     pub fn spawn_chunk_and_get_structure(
         &mut self,
         tile: TileCoordinates,
         chunk_query: &Query<&StructureLayerManager, With<TilemapChunk>>,
+        world_seed: &WorldSeed,
+        chunk_generators: &ChunkGenerators,
         asset_server: &Res<AssetServer>,
         commands: &mut Commands,
         message_recalculate: &mut MessageWriter<RecalculateFlowField>,
@@ -189,6 +222,8 @@ impl MapManager {
         // if the chunk doesn't exists, spawn a new one
         spawn_one_chunk(
             chunk_coord,
+            world_seed,
+            chunk_generators,
             commands,
             asset_server,
             self,
@@ -276,6 +311,61 @@ impl MapManager {
         true
     }
 
+    /// registers a structure spawned on a tile of a loaded chunk and makes it a child of root_entity
+    /// returns false if the chunk isn't loaded or if there is already a structure on the tile
+    pub fn insert_structure(
+        &self,
+        tile: TileCoordinates,
+        structure_entity: Entity,
+        chunk_query: &mut Query<&mut StructureLayerManager, With<TilemapChunk>>,
+        commands: &mut Commands,
+    ) -> bool {
+        let chunk_coord = tile_coord_to_chunk_coord(tile);
+        let Some(chunk_entity) = self.chunks.get(&chunk_coord) else {
+            return false;
+        };
+        let Ok(mut structure_manager) = chunk_query.get_mut(*chunk_entity) else {
+            return false;
+        };
+        let local_tile = tile_coord_to_local_tile_coord(tile, chunk_coord);
+        if structure_manager.structures.contains_key(&local_tile) {
+            return false;
+        }
+        structure_manager
+            .structures
+            .insert(local_tile, structure_entity);
+        commands
+            .entity(self.root_entity)
+            .add_child(structure_entity);
+        true
+    }
+
+    /// unregisters the structure of the tile and returns it, the caller despawns it
+    pub fn remove_structure(
+        &self,
+        tile: TileCoordinates,
+        chunk_query: &mut Query<&mut StructureLayerManager, With<TilemapChunk>>,
+    ) -> Option<Entity> {
+        let chunk_coord = tile_coord_to_chunk_coord(tile);
+        let chunk_entity = self.chunks.get(&chunk_coord)?;
+        let mut structure_manager = chunk_query.get_mut(*chunk_entity).ok()?;
+        let local_tile = tile_coord_to_local_tile_coord(tile, chunk_coord);
+        structure_manager.structures.remove(&local_tile)
+    }
+
+    /// unregisters the resource node of the tile and returns it, the caller despawns it
+    pub fn remove_resource_node(
+        &self,
+        tile: TileCoordinates,
+        chunk_query: &mut Query<&mut ResourceNodeLayerManager, With<TilemapChunk>>,
+    ) -> Option<Entity> {
+        let chunk_coord = tile_coord_to_chunk_coord(tile);
+        let chunk_entity = self.chunks.get(&chunk_coord)?;
+        let mut resource_node_manager = chunk_query.get_mut(*chunk_entity).ok()?;
+        let local_tile = tile_coord_to_local_tile_coord(tile, chunk_coord);
+        resource_node_manager.sources.remove(&local_tile)
+    }
+
     pub fn insert_chunk_and_children(
         &mut self,
         chunk_coord: ChunkCoordinates,
@@ -316,11 +406,16 @@ impl MultiMapManager {
 pub fn spawn_first_chunk_system(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
+    world_seed: Res<WorldSeed>,
+    chunk_generators: Res<ChunkGenerators>,
     mut multi_map_manager: ResMut<MultiMapManager>,
     mut message_recalculate: MessageWriter<RecalculateFlowField>,
 ) {
+    info!("World seed: {}", world_seed.0);
     spawn_one_chunk(
         ChunkCoordinates { x: 0, y: 0 },
+        &world_seed,
+        &chunk_generators,
         &mut commands,
         &asset_server,
         multi_map_manager.maps.get_mut(&MapId(0)).unwrap(),
@@ -328,19 +423,44 @@ pub fn spawn_first_chunk_system(
     );
 }
 
+/// loads the chunk from disk if it has been unloaded before, otherwise generates it
 pub fn spawn_one_chunk(
     chunk_coord: ChunkCoordinates,
+    world_seed: &WorldSeed,
+    chunk_generators: &ChunkGenerators,
     commands: &mut Commands,
     asset_server: &Res<AssetServer>,
     // multi_map_manager: &mut ResMut<MultiMapManager>,
     map_manager: &mut MapManager,
     message_recalculate: &mut MessageWriter<RecalculateFlowField>,
-) -> () {
-    println!("spawn_one_chunk()");
-    let mut rng = rand::rng();
-    // let chunk_coord = ChunkCoordinates { x: 0, y: 0 };
-    let mut structure_layer_manager = StructureLayerManager::default();
-    let mut resource_node_layer_manager = ResourceNodeLayerManager::default();
+) {
+    let map_id = map_manager.map_id;
+    let chunk_save = match read_chunk(map_id, chunk_coord) {
+        Ok(Some(chunk_save)) => chunk_save,
+        Ok(None) => generate_chunk(chunk_coord, map_id, world_seed, chunk_generators),
+        Err(error) => {
+            error!(
+                "Couldn't load chunk {:?}, generating it again: {}",
+                chunk_coord, error
+            );
+            generate_chunk(chunk_coord, map_id, world_seed, chunk_generators)
+        }
+    };
+    chunk_save.spawn(map_manager, commands, asset_server, message_recalculate);
+}
+
+/// the content of the chunk only depends on world_seed, the map and chunk_coord
+pub fn generate_chunk(
+    chunk_coord: ChunkCoordinates,
+    map_id: MapId,
+    world_seed: &WorldSeed,
+    chunk_generators: &ChunkGenerators,
+) -> ChunkSave {
+    let generated_chunk = chunk_generators
+        .get(map_id)
+        .generate(world_seed, map_id, chunk_coord);
+    let mut structures = Vec::new();
+    let mut resource_nodes = Vec::new();
     for x in 0..CHUNK_SIZE.x {
         for y in 0..CHUNK_SIZE.y {
             let local_tile_coord = LocalTileCoordinates {
@@ -348,227 +468,118 @@ pub fn spawn_one_chunk(
                 y: y as i32,
             };
 
-            let is_wall = rng.random_bool(0.2);
-            let is_source = rng.random_bool(0.2);
+            let generated_tile = generated_chunk.get(local_tile_coord);
             if (local_tile_coord.x > 2) && (local_tile_coord.y > 2) {
-                let tile_coord = local_tile_coord_to_tile_coord(local_tile_coord, chunk_coord);
-                if is_wall {
-                    let bundle = StructureBundle::new(
-                        GridPosition(tile_coord),
-                        CollisionEffectCooldown::EVERY_SECOND,
-                    );
-                    let wall_bundle = WallBundle::new(bundle);
-                    let wall_entity = commands
-                        .spawn((
-                            wall_bundle,
-                            Sprite::from_image(
-                                asset_server
-                                    .load(Structure::PATH_PNG_FOLDER.to_owned() + "wall.png"),
-                            ),
-                        ))
-                        .id();
-                    structure_layer_manager
-                        .structures
-                        .insert(local_tile_coord, wall_entity);
-                } else if is_source {
-                    let target_coord = tile_coord_to_absolute_coord(tile_coord);
-                    let transform =
-                        Transform::from_xyz(target_coord.x, target_coord.y, ResourceNode::LAYER);
-                    let mut item_stack = ItemStack {
-                        item_type: ItemType::IronOre,
-                        quality: Quality::Standard,
-                        quantity: 3,
-                    };
-                    let mut sprite = Sprite::from_image(
-                        asset_server
-                            .load(ResourceNode::PATH_PNG_FOLDER.to_owned() + "iron_ore.png"),
-                    );
-                    if rng.random_bool(0.2) {
-                        item_stack = ItemStack {
-                            item_type: ItemType::CopperOre,
-                            quality: Quality::Standard,
-                            quantity: 3,
-                        };
-                        sprite = Sprite::from_image(
-                            asset_server
-                                .load(ResourceNode::PATH_PNG_FOLDER.to_owned() + "copper_ore.png"),
-                        );
-                    }
-                    let resource_node_entity = commands
-                        .spawn((ResourceNode(item_stack), sprite, transform))
-                        .id();
-                    resource_node_layer_manager
-                        .sources
-                        .insert(local_tile_coord, resource_node_entity);
+                if generated_tile.is_wall {
+                    structures.push((local_tile_coord, StructureSave::Wall));
+                } else if let Some(item_type) = generated_tile.resource {
+                    let item_stack = ItemStack::new(item_type, Quality::Standard, 3);
+                    resource_nodes.push((
+                        local_tile_coord,
+                        ResourceNode::new(item_stack, generated_tile.resource_amount),
+                    ));
 
                     if local_tile_coord.x < 5 && local_tile_coord.y < 5 {
-                        let bundle = MiningMachineBundle {
-                            base: MachineBaseBundle {
-                                name: Name::new("Mining machine"),
-                                // structure: Structure,
-                                structure_bundle: StructureBundle::new(
-                                    GridPosition(tile_coord),
-                                    CollisionEffectCooldown::EVERY_SECOND,
-                                ),
-                                direction: Direction::North,
-                                // transform,
-                                machine: Machine::default(),
+                        structures.push((
+                            local_tile_coord,
+                            StructureSave::MiningMachine {
+                                base: MachineSave::new("Mining machine", Direction::North),
+                                mining_machine: MiningMachine,
+                                output_inventory: Inventory::default(),
                             },
-                            output_inventory: OutputInventory::default(),
-                            block_sight: BlockSight,
-                            mining_machine: MiningMachine::new(item_stack),
-                        };
-                        let machine_entity = commands
-                            .spawn((
-                                bundle,
-                                Sprite::from_image(asset_server.load(
-                                    Structure::PATH_PNG_FOLDER.to_owned() + "mining_machine.png",
-                                )),
-                            ))
-                            .id();
-                        structure_layer_manager
-                            .structures
-                            .insert(local_tile_coord, machine_entity);
+                        ));
                     }
                 } else if local_tile_coord.x == 10 && local_tile_coord.y == 10 {
-                    let bundle = PortalBundle::new(
-                        "Portail vers (0, 0)".into(),
-                        GridPosition(tile_coord),
-                        MapId(1),
-                        TileCoordinates { x: 0, y: 0 },
-                    );
-                    let portal_entity = commands
-                        .spawn((
-                            bundle,
-                            Sprite::from_image(
-                                asset_server
-                                    .load(Structure::PATH_PNG_FOLDER.to_owned() + "portal.png"),
-                            ),
-                        ))
-                        .id();
-                    structure_layer_manager
-                        .structures
-                        .insert(local_tile_coord, portal_entity);
+                    structures.push((
+                        local_tile_coord,
+                        StructureSave::Portal {
+                            name: "Portail vers (0, 0)".to_owned(),
+                            portal: Portal {
+                                destination_map_id: MapId(1),
+                                destination_tile_pos: TileCoordinates { x: 0, y: 0 },
+                            },
+                        },
+                    ));
                 } else if local_tile_coord.x < 10 && local_tile_coord.y < 10 {
-                    let bundle = PortalBundle::new(
-                        "Portail vers (10, 10)".into(),
-                        GridPosition(tile_coord),
-                        DEFAULT_MAP_ID,
-                        TileCoordinates { x: 10, y: 10 },
-                    );
-                    let portal_entity = commands
-                        .spawn((
-                            bundle,
-                            Sprite::from_image(
-                                asset_server
-                                    .load(Structure::PATH_PNG_FOLDER.to_owned() + "portal.png"),
-                            ),
-                        ))
-                        .id();
-                    structure_layer_manager
-                        .structures
-                        .insert(local_tile_coord, portal_entity);
+                    structures.push((
+                        local_tile_coord,
+                        StructureSave::Portal {
+                            name: "Portail vers (10, 10)".to_owned(),
+                            portal: Portal {
+                                destination_map_id: DEFAULT_MAP_ID,
+                                destination_tile_pos: TileCoordinates { x: 10, y: 10 },
+                            },
+                        },
+                    ));
                 }
             }
         }
     }
 
-    let local_tile_coord = LocalTileCoordinates { x: 1, y: 1 };
-    let tile_coord = local_tile_coord_to_tile_coord(local_tile_coord, chunk_coord);
-    let item_stack = ItemStack::new(ItemType::IronPlate, Quality::Perfect, 10);
-    let mut input_inventory = InputInventory::default();
-    input_inventory
-        .0
-        .add(item_stack)
-        .expect("add_item_stack() didn't work");
-    let bundle = BeltMachineBundle {
-        base: MachineBaseBundle {
-            name: Name::new("Belt machine"),
-            // structure: Structure,
-            structure_bundle: StructureBundle::new(
-                GridPosition(tile_coord),
-                CollisionEffectCooldown::EVERY_SECOND,
-            ),
-            direction: Direction::North,
-            // transform,
-            machine: Machine::default(),
+    // a short belt line feeding the crafting machine
+    let mut iron_plates = ItemStack::new(ItemType::IRON_PLATE, Quality::Perfect, 10);
+    for y in 1..=2 {
+        let mut belt = Belt::default();
+        iron_plates.quantity = belt.fill(iron_plates);
+        structures.push((
+            LocalTileCoordinates { x: 1, y },
+            StructureSave::Belt {
+                name: "Belt".to_owned(),
+                direction: Direction::North,
+                belt,
+            },
+        ));
+    }
+    structures.push((
+        LocalTileCoordinates { x: 1, y: 0 },
+        StructureSave::CraftingMachine {
+            base: MachineSave::new("Crafting machine", Direction::South),
+            crafting_machine: CraftingMachine::new(RecipeId::IRON_PLATE_TO_IRON_GEAR),
+            input_inventory: Inventory::default(),
+            output_inventory: Inventory::default(),
         },
-        input_inventory,
-        output_inventory: OutputInventory::default(),
-        belt_machine: BeltMachine,
-    };
-    let machine_entity = commands
-        .spawn((
-            bundle,
-            Sprite::from_image(
-                asset_server.load(Structure::PATH_PNG_FOLDER.to_owned() + "belt_machine.png"),
-            ),
-        ))
-        .id();
-    structure_layer_manager
-        .structures
-        .insert(local_tile_coord, machine_entity);
-    let local_tile_coord = LocalTileCoordinates { x: 1, y: 0 };
-    let tile_coord = local_tile_coord_to_tile_coord(local_tile_coord, chunk_coord);
-    let bundle = CraftingMachineBundle {
-        base: MachineBaseBundle {
-            name: Name::new("Crafting machine"),
-            // structure: Structure,
-            structure_bundle: StructureBundle::new(
-                GridPosition(tile_coord),
-                CollisionEffectCooldown::EVERY_SECOND,
-            ),
-            direction: Direction::South,
-            // transform,
-            machine: Machine::default(),
+    ));
+    // powered during the day
+    structures.push((
+        LocalTileCoordinates { x: 0, y: 0 },
+        StructureSave::SolarPanel {
+            name: "Solar panel".to_owned(),
         },
-        input_inventory: InputInventory::default(),
-        output_inventory: OutputInventory::default(),
-        block_sight: BlockSight,
-        crafting_machine: CraftingMachine::new(RecipeId::IronPlateToIronGear),
-    };
-    let machine_entity = commands
-        .spawn((
-            bundle,
-            Sprite::from_image(
-                asset_server.load(Structure::PATH_PNG_FOLDER.to_owned() + "crafting_machine.png"),
-            ),
-        ))
-        .id();
-    structure_layer_manager
-        .structures
-        .insert(local_tile_coord, machine_entity);
-
-    message_recalculate.write_default();
-
-    let tile_data: Vec<Option<TileData>> = (0..CHUNK_SIZE.element_product())
-        // .map(|_| rng.random_range(0..5))
-        .map(|_| rng.random_range(1..2))
-        .map(|i| {
-            if i == 0 {
-                None
-            } else {
-                Some(TileData::from_tileset_index(i - 1))
-            }
-        })
-        .collect();
-
-    let all_children: Vec<Entity> = structure_layer_manager
-        .structures
-        .values()
-        .copied()
-        .chain(resource_node_layer_manager.sources.values().copied())
-        .collect();
-
-    let tilemap_chunk = TilemapChunk::new(asset_server.load("textures/array_texture.png"));
-    let chunk_bundle = ChunkBundle::new(
+    ));
+    structures.push((
+        LocalTileCoordinates { x: 0, y: 1 },
+        StructureSave::PowerPole {
+            name: "Power pole".to_owned(),
+        },
+    ));
+    // an inserter stores the crafted gears in a chest
+    structures.push((
+        LocalTileCoordinates { x: 2, y: 0 },
+        StructureSave::Inserter {
+            name: "Inserter".to_owned(),
+            direction: Direction::East,
+            inserter: Inserter::default(),
+        },
+    ));
+    structures.push((
+        LocalTileCoordinates { x: 3, y: 0 },
+        StructureSave::Chest {
+            name: "Chest".to_owned(),
+            inventory: Inventory::with_slots(Chest::SLOTS),
+        },
+    ));
+
+    ChunkSave {
         chunk_coord,
-        tilemap_chunk,
-        TilemapChunkTileData(tile_data),
-        structure_layer_manager,
-        resource_node_layer_manager,
-    );
-    map_manager.insert_chunk_and_children(chunk_coord, chunk_bundle, &all_children, commands);
+        tiles: generated_chunk
+            .tiles
+            .iter()
+            .map(|generated_tile| Some(generated_tile.ground as u16))
+            .collect(),
+        fog: ChunkFogOfWar::default().grid,
+        structures,
+        resource_nodes,
+    }
 }
 
 fn spawn_chunks_around_units_system(
@@ -576,14 +587,18 @@ fn spawn_chunks_around_units_system(
     mut multi_map_manager: ResMut<MultiMapManager>,
     mut commands: Commands,
     asset_server: Res<AssetServer>,
+    world_seed: Res<WorldSeed>,
+    chunk_generators: Res<ChunkGenerators>,
     mut message_recalculate: MessageWriter<RecalculateFlowField>,
 ) {
-    const SIZE: i32 = 2;
-
     for (unit_transform, current_map_id) in unit_query.iter() {
         let unit_chunk_coord = absolute_coord_to_chunk_coord((*unit_transform).into());
-        for y in (unit_chunk_coord.y - SIZE)..(unit_chunk_coord.y + SIZE) {
-            for x in (unit_chunk_coord.x - SIZE)..(unit_chunk_coord.x + SIZE) {
+        for y in
+            (unit_chunk_coord.y - CHUNK_SPAWN_RADIUS)..(unit_chunk_coord.y + CHUNK_SPAWN_RADIUS)
+        {
+            for x in
+                (unit_chunk_coord.x - CHUNK_SPAWN_RADIUS)..(unit_chunk_coord.x + CHUNK_SPAWN_RADIUS)
+            {
                 let chunk_coord = ChunkCoordinates { x, y };
                 let map_manager = multi_map_manager.maps.get_mut(&current_map_id.0).unwrap();
 
@@ -592,6 +607,8 @@ fn spawn_chunks_around_units_system(
                 }
                 spawn_one_chunk(
                     chunk_coord,
+                    &world_seed,
+                    &chunk_generators,
                     &mut commands,
                     &asset_server,
                     multi_map_manager.maps.get_mut(&current_map_id.0).unwrap(),
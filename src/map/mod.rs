@@ -1,6 +1,8 @@
 pub mod coordinates;
 pub mod fog;
+pub mod generation;
 mod map;
+pub mod persistence;
 pub mod resource_node;
 pub mod structure;
 
@@ -0,0 +1,865 @@
+use bevy::{
+    ecs::query::QueryData,
+    prelude::*,
+    sprite_render::{TileData, TilemapChunk, TilemapChunkTileData},
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, fs, io, path::PathBuf};
+
+use crate::{
+    direction::Direction,
+    items::inventory::{FuelInventory, InputInventory, Inventory, ItemStack, OutputInventory},
+    map::{
+        CHUNK_UNLOAD_RADIUS, ChunkBundle, CurrentMapId, MapId, MapManager, MultiMapManager,
+        ResourceNodeLayerManager, StructureLayerManager, TilemapChunkExt,
+        coordinates::{
+            ChunkCoordinates, GridPosition, LocalTileCoordinates, absolute_coord_to_chunk_coord,
+            local_tile_coord_to_tile_coord,
+        },
+        fog::{ChunkFogOfWar, FogState},
+        resource_node::{ResourceNode, spawn_resource_node},
+        structure::{
+            BlockSight, Structure, StructureBundle, StructureKind, Wall, WallBundle,
+            belt::{Belt, BeltBundle},
+            chest::{Chest, ChestBundle},
+            inserter::{Inserter, InserterBundle},
+            machine::{
+                BurnerMachine, CraftingMachine, CraftingMachineBundle, Machine, MachineBaseBundle,
+                MachineKind, MachineStatus, MiningMachine, MiningMachineBundle,
+            },
+            portal::{Portal, PortalBundle},
+            power::{
+                BurnerGenerator, BurnerGeneratorBundle, PowerConsumer, PowerPole, PowerPoleBundle,
+                SolarPanel, SolarPanelBundle,
+            },
+        },
+    },
+    physics::collision_event::CollisionEffectCooldown,
+    save::write_atomically,
+    units::{Unit, pathfinding::RecalculateFlowField},
+};
+
+/// unloaded chunks are written here and read back when a unit comes close again
+/// files of chunks that are loaded again are kept but outdated: the chunk in the world always wins
+pub const PATH_CHUNKS: &str = "saves/chunks";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MachineSave {
+    pub name: String,
+    pub direction: Direction,
+    pub machine: Machine,
+    /// Some for the burner variant, the others use power
+    #[serde(default)]
+    pub burner: Option<BurnerMachineSave>,
+}
+impl MachineSave {
+    pub fn new(name: &str, direction: Direction) -> Self {
+        Self {
+            name: name.to_owned(),
+            direction,
+            machine: Machine::default(),
+            burner: None,
+        }
+    }
+
+    pub fn with_burner(mut self, consumption: f32) -> Self {
+        self.burner = Some(BurnerMachineSave {
+            burner_machine: BurnerMachine::new(consumption),
+            fuel_inventory: Inventory::with_slots(BurnerMachine::FUEL_SLOTS),
+        });
+        self
+    }
+
+    fn fuel_slots(&self) -> &[ItemStack] {
+        self.burner
+            .as_ref()
+            .map_or(&[], |burner| burner.fuel_inventory.slots.as_slice())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BurnerMachineSave {
+    pub burner_machine: BurnerMachine,
+    pub fuel_inventory: Inventory,
+}
+
+/// burner machines get their fuel, the others are connected to the power network
+fn insert_machine_energy(
+    entity_commands: &mut EntityCommands,
+    burner: Option<BurnerMachineSave>,
+    consumption: f32,
+) {
+    match burner {
+        Some(burner) => {
+            entity_commands.insert((burner.burner_machine, FuelInventory(burner.fuel_inventory)))
+        }
+        None => entity_commands.insert(PowerConsumer::new(consumption)),
+    };
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum StructureSave {
+    Wall,
+    Portal {
+        name: String,
+        portal: Portal,
+    },
+    Belt {
+        name: String,
+        direction: Direction,
+        belt: Belt,
+    },
+    Inserter {
+        name: String,
+        direction: Direction,
+        inserter: Inserter,
+    },
+    Chest {
+        name: String,
+        inventory: Inventory,
+    },
+    PowerPole {
+        name: String,
+    },
+    SolarPanel {
+        name: String,
+    },
+    BurnerGenerator {
+        name: String,
+        burner_generator: BurnerGenerator,
+        fuel_inventory: Inventory,
+    },
+    CraftingMachine {
+        base: MachineSave,
+        crafting_machine: CraftingMachine,
+        input_inventory: Inventory,
+        output_inventory: Inventory,
+    },
+    MiningMachine {
+        base: MachineSave,
+        mining_machine: MiningMachine,
+        output_inventory: Inventory,
+    },
+}
+
+/// every component a structure can have that needs to be saved
+#[derive(QueryData)]
+pub struct StructureSaveQuery {
+    pub name: Option<&'static Name>,
+    pub direction: Option<&'static Direction>,
+    pub machine: Option<&'static Machine>,
+    pub input_inventory: Option<&'static InputInventory>,
+    pub output_inventory: Option<&'static OutputInventory>,
+    pub crafting_machine: Option<&'static CraftingMachine>,
+    pub mining_machine: Option<&'static MiningMachine>,
+    pub portal: Option<&'static Portal>,
+    pub belt: Option<&'static Belt>,
+    pub inserter: Option<&'static Inserter>,
+    pub inventory: Option<&'static Inventory>,
+    pub is_chest: Has<Chest>,
+    pub is_power_pole: Has<PowerPole>,
+    pub is_solar_panel: Has<SolarPanel>,
+    pub burner_generator: Option<&'static BurnerGenerator>,
+    pub fuel_inventory: Option<&'static FuelInventory>,
+    pub burner_machine: Option<&'static BurnerMachine>,
+    pub is_wall: Has<Wall>,
+}
+
+impl StructureSave {
+    /// a new structure built by the player
+    pub fn new_built(kind: StructureKind, direction: Direction) -> Self {
+        match kind {
+            StructureKind::Wall => Self::Wall,
+            StructureKind::Belt => Self::Belt {
+                name: "Belt".to_owned(),
+                direction,
+                belt: Belt::default(),
+            },
+            StructureKind::Inserter => Self::Inserter {
+                name: "Inserter".to_owned(),
+                direction,
+                inserter: Inserter::default(),
+            },
+            StructureKind::Chest => Self::Chest {
+                name: "Chest".to_owned(),
+                inventory: Inventory::with_slots(Chest::SLOTS),
+            },
+            StructureKind::PowerPole => Self::PowerPole {
+                name: "Power pole".to_owned(),
+            },
+            StructureKind::SolarPanel => Self::SolarPanel {
+                name: "Solar panel".to_owned(),
+            },
+            StructureKind::BurnerGenerator => Self::BurnerGenerator {
+                name: "Burner generator".to_owned(),
+                burner_generator: BurnerGenerator::default(),
+                fuel_inventory: Inventory::with_slots(BurnerGenerator::FUEL_SLOTS),
+            },
+            StructureKind::CraftingMachine => Self::CraftingMachine {
+                base: MachineSave::new("Crafting machine", direction),
+                crafting_machine: CraftingMachine::default(),
+                input_inventory: Inventory::default(),
+                output_inventory: Inventory::default(),
+            },
+            StructureKind::MiningMachine => Self::MiningMachine {
+                base: MachineSave::new("Mining machine", direction),
+                mining_machine: MiningMachine,
+                output_inventory: Inventory::default(),
+            },
+            StructureKind::BurnerCraftingMachine => Self::CraftingMachine {
+                base: MachineSave::new("Burner crafting machine", direction)
+                    .with_burner(CraftingMachine::POWER_CONSUMPTION),
+                crafting_machine: CraftingMachine::default(),
+                input_inventory: Inventory::default(),
+                output_inventory: Inventory::default(),
+            },
+            StructureKind::Furnace => Self::CraftingMachine {
+                base: MachineSave::new("Furnace", direction)
+                    .with_burner(CraftingMachine::FURNACE_CONSUMPTION),
+                crafting_machine: CraftingMachine::furnace(),
+                input_inventory: Inventory::default(),
+                output_inventory: Inventory::default(),
+            },
+            StructureKind::BurnerMiningMachine => Self::MiningMachine {
+                base: MachineSave::new("Burner mining machine", direction)
+                    .with_burner(MiningMachine::POWER_CONSUMPTION),
+                mining_machine: MiningMachine,
+                output_inventory: Inventory::default(),
+            },
+        }
+    }
+
+    /// None for the structures the player can't build, they are deconstructed without giving back an item
+    pub fn kind(&self) -> Option<StructureKind> {
+        match self {
+            Self::Wall => Some(StructureKind::Wall),
+            Self::Portal { .. } => None,
+            Self::Belt { .. } => Some(StructureKind::Belt),
+            Self::Inserter { .. } => Some(StructureKind::Inserter),
+            Self::Chest { .. } => Some(StructureKind::Chest),
+            Self::PowerPole { .. } => Some(StructureKind::PowerPole),
+            Self::SolarPanel { .. } => Some(StructureKind::SolarPanel),
+            Self::BurnerGenerator { .. } => Some(StructureKind::BurnerGenerator),
+            Self::CraftingMachine {
+                crafting_machine, ..
+            } if crafting_machine.kind == MachineKind::Furnace => Some(StructureKind::Furnace),
+            Self::CraftingMachine { base, .. } if base.burner.is_some() => {
+                Some(StructureKind::BurnerCraftingMachine)
+            }
+            Self::CraftingMachine { .. } => Some(StructureKind::CraftingMachine),
+            Self::MiningMachine { base, .. } if base.burner.is_some() => {
+                Some(StructureKind::BurnerMiningMachine)
+            }
+            Self::MiningMachine { .. } => Some(StructureKind::MiningMachine),
+        }
+    }
+
+    /// every item stored in the structure, inputs already consumed by a craft in progress are lost
+    pub fn contents(&self) -> Vec<ItemStack> {
+        match self {
+            Self::Wall | Self::Portal { .. } | Self::PowerPole { .. } | Self::SolarPanel { .. } => {
+                Vec::new()
+            }
+            Self::BurnerGenerator { fuel_inventory, .. } => fuel_inventory.slots.clone(),
+            Self::Belt { belt, .. } => belt
+                .lanes
+                .iter()
+                .flatten()
+                .map(|item| ItemStack::new(item.item_type, item.quality, 1))
+                .collect(),
+            Self::Inserter { inserter, .. } => inserter.held.into_iter().collect(),
+            Self::Chest { inventory, .. } => inventory.slots.clone(),
+            Self::CraftingMachine {
+                base,
+                crafting_machine,
+                input_inventory,
+                output_inventory,
+            } => input_inventory
+                .slots
+                .iter()
+                .chain(&output_inventory.slots)
+                .chain(base.fuel_slots())
+                .chain(&crafting_machine.consumed_inputs)
+                .copied()
+                .collect(),
+            Self::MiningMachine {
+                base,
+                output_inventory,
+                ..
+            } => output_inventory
+                .slots
+                .iter()
+                .chain(base.fuel_slots())
+                .copied()
+                .collect(),
+        }
+    }
+
+    /// returns None for structures that can't be saved
+    pub fn capture(item: &StructureSaveQueryItem) -> Option<Self> {
+        if item.is_wall {
+            return Some(Self::Wall);
+        }
+        let name = item
+            .name
+            .map(|name| name.as_str().to_owned())
+            .unwrap_or_default();
+        if let Some(portal) = item.portal {
+            return Some(Self::Portal {
+                name,
+                portal: portal.clone(),
+            });
+        }
+        if let Some(belt) = item.belt {
+            return Some(Self::Belt {
+                name,
+                direction: item.direction.copied().unwrap_or_default(),
+                belt: belt.clone(),
+            });
+        }
+        if item.is_chest {
+            return Some(Self::Chest {
+                name,
+                inventory: item.inventory.cloned().unwrap_or_default(),
+            });
+        }
+        if let Some(inserter) = item.inserter {
+            return Some(Self::Inserter {
+                name,
+                direction: item.direction.copied().unwrap_or_default(),
+                inserter: inserter.clone(),
+            });
+        }
+        if item.is_power_pole {
+            return Some(Self::PowerPole { name });
+        }
+        if item.is_solar_panel {
+            return Some(Self::SolarPanel { name });
+        }
+        if let Some(burner_generator) = item.burner_generator {
+            return Some(Self::BurnerGenerator {
+                name,
+                burner_generator: burner_generator.clone(),
+                fuel_inventory: item
+                    .fuel_inventory
+                    .map(|inventory| inventory.0.clone())
+                    .unwrap_or_default(),
+            });
+        }
+
+        let base = MachineSave {
+            name,
+            direction: item.direction.copied().unwrap_or_default(),
+            machine: item.machine?.clone(),
+            burner: item.burner_machine.map(|burner_machine| BurnerMachineSave {
+                burner_machine: burner_machine.clone(),
+                fuel_inventory: item
+                    .fuel_inventory
+                    .map(|inventory| inventory.0.clone())
+                    .unwrap_or_default(),
+            }),
+        };
+        let input_inventory = item
+            .input_inventory
+            .map(|inventory| inventory.0.clone())
+            .unwrap_or_default();
+        let output_inventory = item
+            .output_inventory
+            .map(|inventory| inventory.0.clone())
+            .unwrap_or_default();
+        if let Some(crafting_machine) = item.crafting_machine {
+            return Some(Self::CraftingMachine {
+                base,
+                crafting_machine: crafting_machine.clone(),
+                input_inventory,
+                output_inventory,
+            });
+        }
+        item.mining_machine
+            .map(|mining_machine| Self::MiningMachine {
+                base,
+                mining_machine: mining_machine.clone(),
+                output_inventory,
+            })
+    }
+
+    pub fn spawn(
+        self,
+        grid_position: GridPosition,
+        commands: &mut Commands,
+        asset_server: &AssetServer,
+    ) -> Entity {
+        let structure_bundle =
+            StructureBundle::new(grid_position, CollisionEffectCooldown::EVERY_SECOND);
+        let sprite = |file_name: &str| {
+            Sprite::from_image(asset_server.load(Structure::PATH_PNG_FOLDER.to_owned() + file_name))
+        };
+        let machine_base = |base: MachineSave| MachineBaseBundle {
+            name: Name::new(base.name),
+            structure_bundle: StructureBundle::new(
+                grid_position,
+                CollisionEffectCooldown::EVERY_SECOND,
+            ),
+            direction: base.direction,
+            machine: base.machine,
+            status: MachineStatus::default(),
+        };
+
+        match self {
+            Self::Wall => commands
+                .spawn((
+                    WallBundle::new(structure_bundle),
+                    sprite(StructureKind::Wall.png_file_name()),
+                ))
+                .id(),
+            Self::Portal { name, portal } => commands
+                .spawn((
+                    PortalBundle::new(
+                        name.into(),
+                        grid_position,
+                        portal.destination_map_id,
+                        portal.destination_tile_pos,
+                    ),
+                    sprite("portal.png"),
+                ))
+                .id(),
+            Self::Belt {
+                name,
+                direction,
+                belt,
+            } => commands
+                .spawn((
+                    BeltBundle::new(name.into(), grid_position, direction, belt),
+                    sprite(StructureKind::Belt.png_file_name()),
+                ))
+                .id(),
+            Self::Inserter {
+                name,
+                direction,
+                inserter,
+            } => commands
+                .spawn((
+                    InserterBundle::new(name.into(), grid_position, direction, inserter),
+                    sprite(StructureKind::Inserter.png_file_name()),
+                ))
+                .id(),
+            Self::Chest { name, inventory } => commands
+                .spawn((
+                    ChestBundle::new(name.into(), grid_position, inventory),
+                    sprite(StructureKind::Chest.png_file_name()),
+                ))
+                .id(),
+            Self::PowerPole { name } => commands
+                .spawn((
+                    PowerPoleBundle::new(name.into(), grid_position),
+                    sprite(StructureKind::PowerPole.png_file_name()),
+                ))
+                .id(),
+            Self::SolarPanel { name } => commands
+                .spawn((
+                    SolarPanelBundle::new(name.into(), grid_position),
+                    sprite(StructureKind::SolarPanel.png_file_name()),
+                ))
+                .id(),
+            Self::BurnerGenerator {
+                name,
+                burner_generator,
+                fuel_inventory,
+            } => commands
+                .spawn((
+                    BurnerGeneratorBundle::new(
+                        name.into(),
+                        grid_position,
+                        burner_generator,
+                        fuel_inventory,
+                    ),
+                    sprite(StructureKind::BurnerGenerator.png_file_name()),
+                ))
+                .id(),
+            Self::CraftingMachine {
+                mut base,
+                crafting_machine,
+                input_inventory,
+                output_inventory,
+            } => {
+                let burner = base.burner.take();
+                let kind = if crafting_machine.kind == MachineKind::Furnace {
+                    StructureKind::Furnace
+                } else if burner.is_some() {
+                    StructureKind::BurnerCraftingMachine
+                } else {
+                    StructureKind::CraftingMachine
+                };
+                let mut entity_commands = commands.spawn((
+                    CraftingMachineBundle {
+                        base: machine_base(base),
+                        input_inventory: InputInventory(input_inventory),
+                        output_inventory: OutputInventory(output_inventory),
+                        block_sight: BlockSight,
+                        crafting_machine,
+                    },
+                    sprite(kind.png_file_name()),
+                ));
+                insert_machine_energy(
+                    &mut entity_commands,
+                    burner,
+                    CraftingMachine::POWER_CONSUMPTION,
+                );
+                entity_commands.id()
+            }
+            Self::MiningMachine {
+                mut base,
+                mining_machine,
+                output_inventory,
+            } => {
+                let burner = base.burner.take();
+                let kind = if burner.is_some() {
+                    StructureKind::BurnerMiningMachine
+                } else {
+                    StructureKind::MiningMachine
+                };
+                let mut entity_commands = commands.spawn((
+                    MiningMachineBundle {
+                        base: machine_base(base),
+                        output_inventory: OutputInventory(output_inventory),
+                        block_sight: BlockSight,
+                        mining_machine,
+                    },
+                    sprite(kind.png_file_name()),
+                ));
+                insert_machine_energy(
+                    &mut entity_commands,
+                    burner,
+                    MiningMachine::POWER_CONSUMPTION,
+                );
+                entity_commands.id()
+            }
+        }
+    }
+}
+
+/// everything needed to spawn a chunk again exactly like it was
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkSave {
+    pub chunk_coord: ChunkCoordinates,
+    /// tileset index of every tile, same order as TilemapChunkTileData
+    pub tiles: Vec<Option<u16>>,
+    pub fog: Vec<FogState>,
+    pub structures: Vec<(LocalTileCoordinates, StructureSave)>,
+    pub resource_nodes: Vec<(LocalTileCoordinates, ResourceNode)>,
+}
+impl ChunkSave {
+    pub fn capture(
+        chunk_coord: ChunkCoordinates,
+        (tile_data, fog, structure_layer_manager, resource_node_layer_manager): ChunkSaveQueryItem,
+        structure_query: &Query<StructureSaveQuery, With<Structure>>,
+        resource_node_query: &Query<&ResourceNode>,
+    ) -> Self {
+        let structures = structure_layer_manager
+            .structures
+            .iter()
+            .filter_map(|(local_tile_coord, entity)| {
+                let item = structure_query.get(*entity).ok()?;
+                Some((*local_tile_coord, StructureSave::capture(&item)?))
+            })
+            .collect();
+        let resource_nodes = resource_node_layer_manager
+            .sources
+            .iter()
+            .filter_map(|(local_tile_coord, entity)| {
+                let resource_node = resource_node_query.get(*entity).ok()?;
+                Some((*local_tile_coord, *resource_node))
+            })
+            .collect();
+
+        Self {
+            chunk_coord,
+            tiles: tile_data
+                .0
+                .iter()
+                .map(|tile| tile.map(|tile| tile.tileset_index))
+                .collect(),
+            fog: fog.grid.clone(),
+            structures,
+            resource_nodes,
+        }
+    }
+
+    /// spawns the chunk with its structures and resource nodes and registers it in map_manager
+    pub fn spawn(
+        self,
+        map_manager: &mut MapManager,
+        commands: &mut Commands,
+        asset_server: &AssetServer,
+        message_recalculate: &mut MessageWriter<RecalculateFlowField>,
+    ) {
+        let chunk_coord = self.chunk_coord;
+        let mut structure_layer_manager = StructureLayerManager::default();
+        for (local_tile_coord, structure_save) in self.structures {
+            let tile_coord = local_tile_coord_to_tile_coord(local_tile_coord, chunk_coord);
+            let entity = structure_save.spawn(GridPosition(tile_coord), commands, asset_server);
+            // machines and belts look for their neighbours in the map they are in
+            commands
+                .entity(entity)
+                .insert(CurrentMapId(map_manager.map_id));
+            structure_layer_manager
+                .structures
+                .insert(local_tile_coord, entity);
+        }
+        let mut resource_node_layer_manager = ResourceNodeLayerManager::default();
+        for (local_tile_coord, resource_node) in self.resource_nodes {
+            let tile_coord = local_tile_coord_to_tile_coord(local_tile_coord, chunk_coord);
+            let entity = spawn_resource_node(resource_node, tile_coord, commands, asset_server);
+            resource_node_layer_manager
+                .sources
+                .insert(local_tile_coord, entity);
+        }
+
+        message_recalculate.write_default();
+
+        let tile_data = self
+            .tiles
+            .into_iter()
+            .map(|tileset_index| tileset_index.map(TileData::from_tileset_index))
+            .collect();
+        let all_children: Vec<Entity> = structure_layer_manager
+            .structures
+            .values()
+            .copied()
+            .chain(resource_node_layer_manager.sources.values().copied())
+            .collect();
+
+        let tilemap_chunk = TilemapChunk::new(asset_server.load("textures/array_texture.png"));
+        let mut chunk_bundle = ChunkBundle::new(
+            chunk_coord,
+            tilemap_chunk,
+            TilemapChunkTileData(tile_data),
+            structure_layer_manager,
+            resource_node_layer_manager,
+        );
+        if self.fog.len() == chunk_bundle.chunk_for_of_war.grid.len() {
+            chunk_bundle.chunk_for_of_war.grid = self.fog;
+        }
+        map_manager.insert_chunk_and_children(chunk_coord, chunk_bundle, &all_children, commands);
+    }
+}
+
+pub type ChunkSaveQuery = (
+    &'static TilemapChunkTileData,
+    &'static ChunkFogOfWar,
+    &'static StructureLayerManager,
+    &'static ResourceNodeLayerManager,
+);
+pub type ChunkSaveQueryItem<'a> = (
+    &'a TilemapChunkTileData,
+    &'a ChunkFogOfWar,
+    &'a StructureLayerManager,
+    &'a ResourceNodeLayerManager,
+);
+
+pub fn chunk_path(map_id: MapId, chunk_coord: ChunkCoordinates) -> PathBuf {
+    PathBuf::from(format!(
+        "{}/map_{}/{}_{}.json",
+        PATH_CHUNKS, map_id.0, chunk_coord.x, chunk_coord.y
+    ))
+}
+
+pub fn write_chunk(map_id: MapId, chunk_save: &ChunkSave) -> io::Result<()> {
+    let json = serde_json::to_vec(chunk_save).map_err(io::Error::other)?;
+    // atomic because autosaves read the cache from another thread
+    write_atomically(&chunk_path(map_id, chunk_save.chunk_coord), &json)
+}
+
+/// returns Ok(None) if the chunk has never been unloaded
+pub fn read_chunk(map_id: MapId, chunk_coord: ChunkCoordinates) -> io::Result<Option<ChunkSave>> {
+    let json = match fs::read_to_string(chunk_path(map_id, chunk_coord)) {
+        Ok(json) => json,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error),
+    };
+    Ok(Some(serde_json::from_str(&json).map_err(io::Error::other)?))
+}
+
+/// every chunk of the map on disk except the ones in skipped_chunks (usually because they are loaded)
+pub fn read_cached_chunks(
+    map_id: MapId,
+    skipped_chunks: &HashSet<ChunkCoordinates>,
+) -> io::Result<Vec<ChunkSave>> {
+    let directory = chunk_path(map_id, ChunkCoordinates::default())
+        .parent()
+        .map(PathBuf::from)
+        .unwrap_or_default();
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error),
+    };
+
+    let mut chunk_saves = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        // skips temporary files of writes in progress
+        if path.extension().is_none_or(|extension| extension != "json") {
+            continue;
+        }
+        let json = fs::read_to_string(path)?;
+        let chunk_save: ChunkSave = serde_json::from_str(&json).map_err(io::Error::other)?;
+        if !skipped_chunks.contains(&chunk_save.chunk_coord) {
+            chunk_saves.push(chunk_save);
+        }
+    }
+    Ok(chunk_saves)
+}
+
+/// only the chunks of the map currently in the world
+pub fn capture_loaded_chunks(
+    map_manager: &MapManager,
+    chunk_query: &Query<ChunkSaveQuery, With<TilemapChunk>>,
+    structure_query: &Query<StructureSaveQuery, With<Structure>>,
+    resource_node_query: &Query<&ResourceNode>,
+) -> Vec<ChunkSave> {
+    let mut chunk_saves = Vec::new();
+    for (chunk_coord, chunk_entity) in map_manager.chunks.iter() {
+        if let Ok(chunk_item) = chunk_query.get(*chunk_entity) {
+            chunk_saves.push(ChunkSave::capture(
+                *chunk_coord,
+                chunk_item,
+                structure_query,
+                resource_node_query,
+            ));
+        }
+    }
+    chunk_saves
+}
+
+pub fn clear_chunk_cache() -> io::Result<()> {
+    match fs::remove_dir_all(PATH_CHUNKS) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+        _ => Ok(()),
+    }
+}
+
+/// chunks left by a previous session belong to another world
+pub fn clear_chunk_cache_system() {
+    if let Err(error) = clear_chunk_cache() {
+        error!("Couldn't clear {}: {}", PATH_CHUNKS, error);
+    }
+}
+
+/// saves to disk and despawns chunks that are too far from every unit of their map
+pub fn unload_far_chunks_system(
+    unit_query: Query<(&Transform, &CurrentMapId), With<Unit>>,
+    mut multi_map_manager: ResMut<MultiMapManager>,
+    chunk_query: Query<ChunkSaveQuery, With<TilemapChunk>>,
+    structure_query: Query<StructureSaveQuery, With<Structure>>,
+    resource_node_query: Query<&ResourceNode>,
+    mut commands: Commands,
+) {
+    let unit_chunks: Vec<(MapId, ChunkCoordinates)> = unit_query
+        .iter()
+        .map(|(transform, current_map_id)| {
+            (
+                current_map_id.0,
+                absolute_coord_to_chunk_coord((*transform).into()),
+            )
+        })
+        .collect();
+
+    for (map_id, map_manager) in multi_map_manager.maps.iter_mut() {
+        let far_chunks: Vec<ChunkCoordinates> = map_manager
+            .chunks
+            .keys()
+            .copied()
+            .filter(|chunk_coord| {
+                !unit_chunks.iter().any(|(unit_map_id, unit_chunk)| {
+                    unit_map_id == map_id
+                        && (chunk_coord.x - unit_chunk.x).abs() <= CHUNK_UNLOAD_RADIUS
+                        && (chunk_coord.y - unit_chunk.y).abs() <= CHUNK_UNLOAD_RADIUS
+                })
+            })
+            .collect();
+
+        for chunk_coord in far_chunks {
+            let Some(chunk_entity) = map_manager.chunks.get(&chunk_coord).copied() else {
+                continue;
+            };
+            // the chunk was spawned this tick and isn't in the world yet
+            let Ok(chunk_item) = chunk_query.get(chunk_entity) else {
+                continue;
+            };
+
+            let chunk_save = ChunkSave::capture(
+                chunk_coord,
+                chunk_item,
+                &structure_query,
+                &resource_node_query,
+            );
+            if let Err(error) = write_chunk(*map_id, &chunk_save) {
+                error!("Couldn't save chunk {:?}: {}", chunk_coord, error);
+                continue;
+            }
+
+            let (_, _, structure_layer_manager, resource_node_layer_manager) = chunk_item;
+            for entity in structure_layer_manager
+                .structures
+                .values()
+                .chain(resource_node_layer_manager.sources.values())
+            {
+                commands.entity(*entity).despawn();
+            }
+            commands.entity(chunk_entity).despawn();
+            map_manager.chunks.remove(&chunk_coord);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        items::{ItemType, Quality},
+        map::{coordinates::TileCoordinates, structure::belt::BeltItem},
+    };
+
+    #[test]
+    fn test_structure_contents() {
+        let plate = ItemStack::new(ItemType::IRON_PLATE, Quality::Standard, 1);
+        let mut belt = Belt::default();
+        for lane in 0..2 {
+            belt.lanes[lane].push(BeltItem {
+                item_type: plate.item_type,
+                quality: plate.quality,
+                position: 0.5,
+            });
+        }
+        let belt_save = StructureSave::Belt {
+            name: "Belt".to_owned(),
+            direction: Direction::North,
+            belt,
+        };
+        assert_eq!(belt_save.kind(), Some(StructureKind::Belt));
+        assert_eq!(belt_save.contents(), vec![plate, plate]);
+
+        let inserter_save = StructureSave::Inserter {
+            name: "Inserter".to_owned(),
+            direction: Direction::East,
+            inserter: Inserter {
+                held: Some(plate),
+                ..default()
+            },
+        };
+        assert_eq!(inserter_save.contents(), vec![plate]);
+        assert!(StructureSave::Wall.contents().is_empty());
+
+        // deconstructed without giving back a build item
+        let portal_save = StructureSave::Portal {
+            name: "Portal".to_owned(),
+            portal: Portal {
+                destination_map_id: MapId(1),
+                destination_tile_pos: TileCoordinates { x: 0, y: 0 },
+            },
+        };
+        assert_eq!(portal_save.kind(), None);
+        assert!(portal_save.contents().is_empty());
+    }
+}
@@ -1,11 +1,89 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
-use crate::items::inventory::ItemStack;
+use crate::{
+    items::{ItemType, inventory::ItemStack},
+    map::coordinates::{TileCoordinates, tile_coord_to_absolute_coord},
+};
 
 /// a tile on the map where mining machine can extract ressources
-#[derive(Component)]
-pub struct ResourceNode(pub ItemStack);
+/// mined_item is what one extraction gives, amount is what is left before the node is depleted
+#[derive(Component, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ResourceNode {
+    pub mined_item: ItemStack,
+    pub amount: u32,
+}
 impl ResourceNode {
     pub const LAYER: f32 = -0.1;
     pub const PATH_PNG_FOLDER: &'static str = "tiles/resource_nodes/";
+
+    pub fn new(mined_item: ItemStack, amount: u32) -> Self {
+        Self { mined_item, amount }
+    }
+
+    /// what the next extraction of at most quantity items gives, None once depleted
+    pub fn peek(&self, quantity: u32) -> Option<ItemStack> {
+        let quantity = quantity.min(self.amount);
+        (quantity > 0).then_some(ItemStack {
+            quantity,
+            ..self.mined_item
+        })
+    }
+
+    /// removes what peek() returned from the node
+    pub fn extract(&mut self, quantity: u32) -> Option<ItemStack> {
+        let item_stack = self.peek(quantity)?;
+        self.amount -= item_stack.quantity;
+        Some(item_stack)
+    }
+
+    pub fn is_depleted(&self) -> bool {
+        self.amount == 0
+    }
+
+    pub fn sprite_path(item_type: ItemType) -> String {
+        let file_name = match item_type {
+            ItemType::COPPER_ORE => "copper_ore.png",
+            ItemType::COAL => "coal.png",
+            _ => "iron_ore.png",
+        };
+        Self::PATH_PNG_FOLDER.to_owned() + file_name
+    }
+}
+
+pub fn spawn_resource_node(
+    resource_node: ResourceNode,
+    tile_coord: TileCoordinates,
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+) -> Entity {
+    let target_coord = tile_coord_to_absolute_coord(tile_coord);
+    let transform = Transform::from_xyz(target_coord.x, target_coord.y, ResourceNode::LAYER);
+    let sprite = Sprite::from_image(asset_server.load(ResourceNode::sprite_path(
+        resource_node.mined_item.item_type,
+    )));
+    commands.spawn((resource_node, sprite, transform)).id()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::items::Quality;
+
+    #[test]
+    fn test_resource_node_depletes() {
+        let mut resource_node =
+            ResourceNode::new(ItemStack::new(ItemType::IRON_ORE, Quality::Standard, 3), 4);
+        assert_eq!(
+            resource_node.extract(3).map(|stack| stack.quantity),
+            Some(3)
+        );
+        // the last extraction only gives what is left
+        assert_eq!(
+            resource_node.extract(3).map(|stack| stack.quantity),
+            Some(1)
+        );
+        assert!(resource_node.is_depleted());
+        assert_eq!(resource_node.extract(3), None);
+    }
 }
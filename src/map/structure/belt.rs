@@ -0,0 +1,419 @@
+use crate::{
+    FixedSet, GameSet,
+    direction::Direction,
+    items::{
+        ItemType, Quality,
+        inventory::{InputInventory, ItemStack},
+        registry::ItemRegistry,
+    },
+    loading::LoadingState,
+    map::{
+        CurrentMapId, MultiMapManager, StructureLayerManager, TILE_SIZE,
+        coordinates::{GridPosition, TileCoordinates},
+        structure::{StructureBundle, machine::transfert_items_to_next_machine_system},
+    },
+    physics::{collision_event::CollisionEffectCooldown, movement::Passable},
+    time::GameTime,
+};
+use bevy::{prelude::*, sprite_render::TilemapChunk};
+use serde::{Deserialize, Serialize};
+
+pub struct BeltPlugin;
+impl Plugin for BeltPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            update_belt_item_sprites_system.in_set(GameSet::Visual),
+        )
+        .add_systems(
+            FixedUpdate,
+            move_belt_items_system
+                .after(transfert_items_to_next_machine_system)
+                .in_set(FixedSet::Process)
+                .run_if(in_state(LoadingState::Ready)),
+        );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BeltItem {
+    pub item_type: ItemType,
+    pub quality: Quality,
+    /// 0.0 = back of the tile, 1.0 = front of the tile
+    pub position: f32,
+}
+
+/// a conveyor tile, items move along its direction on two lanes and go to the structure in front
+#[derive(Component, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Belt {
+    /// 0 = left lane, 1 = right lane (relative to the direction of the belt), sorted by position
+    pub lanes: [Vec<BeltItem>; 2],
+}
+impl Belt {
+    /// tiles per tick, 2 tiles per second
+    pub const SPEED: f32 = 2.0 / GameTime::TICKS_PER_SECOND as f32;
+    /// minimal distance between two items of a lane, 4 items per lane and per tile
+    pub const ITEM_SPACING: f32 = 0.25;
+    /// distance between the center of the tile and a lane, in tiles
+    pub const LANE_OFFSET: f32 = 0.25;
+    /// position where side-loaded items enter
+    pub const SIDE_ENTRY_POSITION: f32 = 0.5;
+
+    pub fn can_insert(&self, lane: usize, position: f32) -> bool {
+        self.lanes[lane]
+            .iter()
+            .all(|item| (item.position - position).abs() >= Self::ITEM_SPACING - f32::EPSILON)
+    }
+
+    /// returns false if the item is too close to another one
+    pub fn insert(&mut self, lane: usize, item: BeltItem) -> bool {
+        if !self.can_insert(lane, item.position) {
+            return false;
+        }
+        let lane = &mut self.lanes[lane];
+        let index = lane.partition_point(|other| other.position < item.position);
+        lane.insert(index, item);
+        true
+    }
+
+    /// fills the belt from its front with up to 4 items per lane, alternating lanes, returns what didn't fit
+    pub fn fill(&mut self, item_stack: ItemStack) -> u32 {
+        let mut quantity = item_stack.quantity;
+        let mut position = 1.0;
+        while quantity > 0 && position > 0.0 {
+            for lane in 0..self.lanes.len() {
+                if quantity > 0 {
+                    let item = BeltItem {
+                        item_type: item_stack.item_type,
+                        quality: item_stack.quality,
+                        position,
+                    };
+                    if self.insert(lane, item) {
+                        quantity -= 1;
+                    }
+                }
+            }
+            position -= Self::ITEM_SPACING;
+        }
+        quantity
+    }
+
+    /// the item at the front of the lane if it reached the end of the tile
+    pub fn leaving_item(&self, lane: usize) -> Option<BeltItem> {
+        self.lanes[lane]
+            .last()
+            .filter(|item| item.position >= 1.0)
+            .copied()
+    }
+
+    pub fn item_count(&self) -> usize {
+        self.lanes.iter().map(Vec::len).sum()
+    }
+
+    /// moves the items of every lane, items stop behind the previous one and at the end of the tile
+    pub fn advance(&mut self, distance: f32) {
+        for lane in &mut self.lanes {
+            advance_lane(lane, distance);
+        }
+    }
+}
+
+fn advance_lane(lane: &mut [BeltItem], distance: f32) {
+    let mut limit = 1.0;
+    for item in lane.iter_mut().rev() {
+        item.position = (item.position + distance).min(limit).max(item.position);
+        limit = item.position - Belt::ITEM_SPACING;
+    }
+}
+
+/// where an item arriving on a belt enters it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BeltEntry {
+    /// from behind, at the back of the tile, on any lane
+    Back,
+    /// from a side, in the middle of the tile on the lane of that side
+    Side(usize),
+}
+impl BeltEntry {
+    /// from: direction the item moves in, to: direction of the receiving belt, None for head-on
+    pub fn new(from: Direction, to: Direction) -> Option<Self> {
+        if from == to {
+            Some(Self::Back)
+        } else if from == to.opposite() {
+            None
+        } else if from.opposite() == to.turned_left() {
+            Some(Self::Side(0))
+        } else {
+            Some(Self::Side(1))
+        }
+    }
+
+    /// the lane and position for an item leaving from lane, a curve keeps the lane of the item
+    pub fn slot(self, lane: usize, is_curve: bool) -> (usize, f32) {
+        match self {
+            Self::Back => (lane, 0.0),
+            Self::Side(_) if is_curve => (lane, 0.0),
+            Self::Side(side_lane) => (side_lane, Belt::SIDE_ENTRY_POSITION),
+        }
+    }
+}
+
+#[derive(Bundle)]
+pub struct BeltBundle {
+    pub name: Name,
+    pub structure_bundle: StructureBundle,
+    pub direction: Direction,
+    pub passable: Passable,
+    pub belt: Belt,
+}
+impl BeltBundle {
+    pub fn new(name: Name, grid_position: GridPosition, direction: Direction, belt: Belt) -> Self {
+        Self {
+            name,
+            structure_bundle: StructureBundle::new(grid_position, CollisionEffectCooldown::Never),
+            direction,
+            passable: Passable,
+            belt,
+        }
+    }
+}
+
+/// child of a belt displaying one of its items
+#[derive(Component)]
+pub struct BeltItemSprite(pub ItemType);
+
+pub fn tile_in_front(tile: TileCoordinates, direction: Direction) -> TileCoordinates {
+    let delta = direction.to_ivec2();
+    TileCoordinates {
+        x: tile.x + delta.x,
+        y: tile.y + delta.y,
+    }
+}
+
+/// a side-loaded belt turns into a curve when nothing feeds it from behind or from its other side
+pub fn is_curve(
+    target_tile: TileCoordinates,
+    target_direction: Direction,
+    from: Direction,
+    belt_direction_at: impl Fn(TileCoordinates) -> Option<Direction>,
+) -> bool {
+    let feeds_target = |direction: Direction| {
+        belt_direction_at(tile_in_front(target_tile, direction.opposite())) == Some(direction)
+    };
+    !feeds_target(target_direction) && !feeds_target(from.opposite())
+}
+
+/// every belt moves its items before the hand-offs, so an item moves at most Belt::SPEED per tick whatever the order of the belts
+pub fn move_belt_items_system(
+    mut belt_query: Query<(Entity, &GridPosition, &Direction, &mut Belt, &CurrentMapId)>,
+    mut input_inventory_query: Query<&mut InputInventory, Without<Belt>>,
+    chunk_query: Query<&StructureLayerManager, With<TilemapChunk>>,
+    multi_map_manager: Res<MultiMapManager>,
+    item_registry: Res<ItemRegistry>,
+) {
+    for (_, _, _, mut belt, _) in belt_query.iter_mut() {
+        if belt.item_count() > 0 {
+            belt.advance(Belt::SPEED);
+        }
+    }
+
+    let belt_entities: Vec<Entity> = belt_query.iter().map(|(entity, ..)| entity).collect();
+    for belt_entity in belt_entities {
+        let Ok((_, grid_position, direction, belt, current_map_id)) = belt_query.get(belt_entity)
+        else {
+            continue;
+        };
+
+        let leaving_items: Vec<(usize, BeltItem)> = (0..belt.lanes.len())
+            .filter_map(|lane| Some((lane, belt.leaving_item(lane)?)))
+            .collect();
+        if leaving_items.is_empty() {
+            continue;
+        }
+        let direction = *direction;
+        let target_tile = tile_in_front(grid_position.0, direction);
+        let Some(map_manager) = multi_map_manager.maps.get(&current_map_id.0) else {
+            continue;
+        };
+        let Some(target_entity) = map_manager.get_structure(target_tile, &chunk_query) else {
+            continue;
+        };
+
+        let mut left_lanes = Vec::new();
+        if let Ok(mut input_inventory) = input_inventory_query.get_mut(target_entity) {
+            for (lane, item) in leaving_items {
+                let item_stack = ItemStack::new(item.item_type, item.quality, 1);
+                if input_inventory.0.add(item_stack, &item_registry).is_ok() {
+                    left_lanes.push(lane);
+                }
+            }
+        } else if let Ok((_, _, &target_direction, _, _)) = belt_query.get(target_entity) {
+            let Some(entry) = BeltEntry::new(direction, target_direction) else {
+                continue;
+            };
+            let is_curve = is_curve(target_tile, target_direction, direction, |tile| {
+                let entity = map_manager.get_structure(tile, &chunk_query)?;
+                belt_query
+                    .get(entity)
+                    .ok()
+                    .map(|(_, _, &direction, _, _)| direction)
+            });
+            let Ok((_, _, _, mut target_belt, _)) = belt_query.get_mut(target_entity) else {
+                continue;
+            };
+            for (lane, item) in leaving_items {
+                let (target_lane, position) = entry.slot(lane, is_curve);
+                if target_belt.insert(target_lane, BeltItem { position, ..item }) {
+                    left_lanes.push(lane);
+                }
+            }
+        }
+
+        if left_lanes.is_empty() {
+            continue;
+        }
+        let Ok((_, _, _, mut belt, _)) = belt_query.get_mut(belt_entity) else {
+            continue;
+        };
+        for lane in left_lanes {
+            belt.lanes[lane].pop();
+        }
+    }
+}
+
+/// keeps one child sprite per item of the belt, placed on its lane
+pub fn update_belt_item_sprites_system(
+    mut commands: Commands,
+    belt_query: Query<(Entity, &Belt, Option<&Children>), Changed<Belt>>,
+    mut sprite_query: Query<(&mut Transform, &mut Sprite, &mut BeltItemSprite)>,
+    item_registry: Res<ItemRegistry>,
+    asset_server: Res<AssetServer>,
+) {
+    let item_size = Vec2::new(TILE_SIZE.x as f32, TILE_SIZE.y as f32) * 0.4;
+    let image = |item_type: ItemType| {
+        let path = item_registry
+            .get(item_type)
+            .map_or("default.png", |definition| definition.sprite.as_str());
+        asset_server.load(path.to_owned())
+    };
+
+    for (belt_entity, belt, children) in belt_query.iter() {
+        let mut item_sprites: Vec<Entity> = children
+            .into_iter()
+            .flatten()
+            .copied()
+            .filter(|child| sprite_query.contains(*child))
+            .collect();
+
+        for (lane, lane_items) in belt.lanes.iter().enumerate() {
+            // le sprite de la belt pointe vers le haut, la voie de gauche est donc à x < 0
+            let lane_x =
+                if lane == 0 { -1.0 } else { 1.0 } * Belt::LANE_OFFSET * TILE_SIZE.x as f32;
+            for item in lane_items {
+                let translation =
+                    Vec3::new(lane_x, (item.position - 0.5) * TILE_SIZE.y as f32, 0.1);
+                match item_sprites.pop() {
+                    Some(sprite_entity) => {
+                        let Ok((mut transform, mut sprite, mut item_sprite)) =
+                            sprite_query.get_mut(sprite_entity)
+                        else {
+                            continue;
+                        };
+                        transform.translation = translation;
+                        if item_sprite.0 != item.item_type {
+                            item_sprite.0 = item.item_type;
+                            sprite.image = image(item.item_type);
+                        }
+                    }
+                    None => {
+                        let sprite_entity = commands
+                            .spawn((
+                                Sprite {
+                                    image: image(item.item_type),
+                                    custom_size: Some(item_size),
+                                    ..default()
+                                },
+                                Transform::from_translation(translation),
+                                BeltItemSprite(item.item_type),
+                            ))
+                            .id();
+                        commands.entity(belt_entity).add_child(sprite_entity);
+                    }
+                }
+            }
+        }
+
+        for sprite_entity in item_sprites {
+            commands.entity(sprite_entity).despawn();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(position: f32) -> BeltItem {
+        BeltItem {
+            item_type: ItemType::IRON_PLATE,
+            quality: Quality::Standard,
+            position,
+        }
+    }
+
+    fn positions(belt: &Belt, lane: usize) -> Vec<f32> {
+        belt.lanes[lane].iter().map(|item| item.position).collect()
+    }
+
+    #[test]
+    fn test_insert_respects_spacing() {
+        let mut belt = Belt::default();
+        assert!(belt.insert(0, item(0.5)));
+        assert!(!belt.insert(0, item(0.6)));
+        assert!(belt.insert(0, item(0.0)));
+        assert!(belt.insert(0, item(0.25)));
+        // the other lane is independent
+        assert!(belt.insert(1, item(0.6)));
+        assert_eq!(positions(&belt, 0), vec![0.0, 0.25, 0.5]);
+    }
+
+    #[test]
+    fn test_advance_compresses_lane() {
+        let mut belt = Belt::default();
+        belt.insert(0, item(0.9));
+        belt.insert(0, item(0.5));
+        belt.advance(0.3);
+        // the front item stops at the end of the tile, the next one behind it
+        assert_eq!(positions(&belt, 0), vec![0.75, 1.0]);
+        assert!(belt.leaving_item(0).is_some());
+        assert!(belt.leaving_item(1).is_none());
+
+        belt.advance(0.3);
+        assert_eq!(positions(&belt, 0), vec![0.75, 1.0]);
+    }
+
+    #[test]
+    fn test_belt_entry() {
+        use Direction::*;
+        assert_eq!(BeltEntry::new(North, North), Some(BeltEntry::Back));
+        assert_eq!(BeltEntry::new(North, South), None);
+        // an item moving east arrives on the left side of a belt going north
+        assert_eq!(BeltEntry::new(East, North), Some(BeltEntry::Side(0)));
+        assert_eq!(BeltEntry::new(West, North), Some(BeltEntry::Side(1)));
+
+        assert_eq!(
+            BeltEntry::Side(0).slot(1, false),
+            (0, Belt::SIDE_ENTRY_POSITION)
+        );
+        assert_eq!(BeltEntry::Side(0).slot(1, true), (1, 0.0));
+    }
+
+    #[test]
+    fn test_fill() {
+        let mut belt = Belt::default();
+        let left = belt.fill(ItemStack::new(ItemType::IRON_PLATE, Quality::Perfect, 10));
+        assert_eq!(left, 2);
+        assert_eq!(belt.item_count(), 8);
+        assert_eq!(positions(&belt, 1), vec![0.25, 0.5, 0.75, 1.0]);
+    }
+}
@@ -0,0 +1,33 @@
+use crate::{
+    items::inventory::Inventory,
+    map::{coordinates::GridPosition, structure::StructureBundle},
+    physics::collision_event::CollisionEffectCooldown,
+};
+use bevy::prelude::*;
+
+/// storage structure, machines and inserters put items in its Inventory and inserters take them out
+#[derive(Component, Default)]
+pub struct Chest;
+impl Chest {
+    pub const SLOTS: u32 = 32;
+}
+#[derive(Bundle)]
+pub struct ChestBundle {
+    pub name: Name,
+    pub structure_bundle: StructureBundle,
+    pub inventory: Inventory,
+    pub chest: Chest,
+}
+impl ChestBundle {
+    pub fn new(name: Name, grid_position: GridPosition, inventory: Inventory) -> Self {
+        Self {
+            name,
+            structure_bundle: StructureBundle::new(
+                grid_position,
+                CollisionEffectCooldown::EVERY_SECOND,
+            ),
+            inventory,
+            chest: Chest,
+        }
+    }
+}
@@ -0,0 +1,309 @@
+use crate::{
+    FixedSet,
+    direction::Direction,
+    items::{
+        ItemType,
+        inventory::{FuelInventory, InputInventory, Inventory, ItemStack, OutputInventory},
+        registry::ItemRegistry,
+    },
+    loading::LoadingState,
+    map::{
+        CurrentMapId, MultiMapManager, StructureLayerManager,
+        coordinates::GridPosition,
+        structure::{
+            StructureBundle,
+            belt::{Belt, BeltEntry, BeltItem, tile_in_front},
+            chest::Chest,
+            machine::transfert_items_to_next_machine_system,
+        },
+    },
+    physics::collision_event::CollisionEffectCooldown,
+    time::GameTime,
+};
+use bevy::{prelude::*, sprite_render::TilemapChunk};
+use serde::{Deserialize, Serialize};
+
+pub struct InserterPlugin;
+impl Plugin for InserterPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            process_inserters_system
+                .after(transfert_items_to_next_machine_system)
+                .in_set(FixedSet::Process)
+                .run_if(in_state(LoadingState::Ready)),
+        );
+    }
+}
+
+/// an arm taking items from the tile behind it and dropping them on the tile in front of it
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+pub struct Inserter {
+    /// maximum quantity carried in one swing
+    pub hand_size: u32,
+    /// ticks to swing from the source to the target, the same to come back
+    pub swing_time_ticks: u64,
+    /// only these items are picked, every item if empty
+    #[serde(default)]
+    pub filter: Vec<ItemType>,
+    #[serde(default)]
+    pub held: Option<ItemStack>,
+    /// 0 = above the source, swing_time_ticks = above the target
+    #[serde(default)]
+    pub swing_progress_ticks: u64,
+}
+impl Inserter {
+    pub const DEFAULT_HAND_SIZE: u32 = 1;
+    pub const DEFAULT_SWING_TIME_TICKS: u64 = GameTime::TICKS_PER_SECOND / 2;
+
+    pub fn accepts(&self, item_type: ItemType) -> bool {
+        self.filter.is_empty() || self.filter.contains(&item_type)
+    }
+
+    /// adds the item to the filter, or removes it if it is already there
+    pub fn toggle_filter(&mut self, item_type: ItemType) {
+        match self
+            .filter
+            .iter()
+            .position(|filtered| *filtered == item_type)
+        {
+            Some(index) => {
+                self.filter.remove(index);
+            }
+            None => self.filter.push(item_type),
+        }
+    }
+
+    /// takes up to hand_size items of the first accepted slot
+    pub fn take_from_inventory(&self, inventory: &mut Inventory) -> Option<ItemStack> {
+        let slot = *inventory
+            .slots
+            .iter()
+            .find(|slot| self.accepts(slot.item_type))?;
+        let quantity = slot.quantity.min(self.hand_size);
+        Some(inventory.remove(ItemStack { quantity, ..slot }))
+    }
+
+    /// takes up to hand_size items from the front of the lanes, all of the same item and quality
+    pub fn take_from_belt(&self, belt: &mut Belt) -> Option<ItemStack> {
+        let mut held: Option<ItemStack> = None;
+        for lane in belt.lanes.iter_mut() {
+            while held.is_none_or(|held| held.quantity < self.hand_size) {
+                let Some(&item) = lane.last() else {
+                    break;
+                };
+                if !self.accepts(item.item_type) {
+                    break;
+                }
+                match &mut held {
+                    None => held = Some(ItemStack::new(item.item_type, item.quality, 1)),
+                    Some(held)
+                        if held.item_type == item.item_type && held.quality == item.quality =>
+                    {
+                        held.quantity += 1
+                    }
+                    Some(_) => break,
+                }
+                lane.pop();
+            }
+        }
+        held
+    }
+}
+impl Default for Inserter {
+    fn default() -> Self {
+        Self {
+            hand_size: Self::DEFAULT_HAND_SIZE,
+            swing_time_ticks: Self::DEFAULT_SWING_TIME_TICKS,
+            filter: Vec::new(),
+            held: None,
+            swing_progress_ticks: 0,
+        }
+    }
+}
+
+#[derive(Bundle)]
+pub struct InserterBundle {
+    pub name: Name,
+    pub structure_bundle: StructureBundle,
+    pub direction: Direction,
+    pub inserter: Inserter,
+}
+impl InserterBundle {
+    pub fn new(
+        name: Name,
+        grid_position: GridPosition,
+        direction: Direction,
+        inserter: Inserter,
+    ) -> Self {
+        Self {
+            name,
+            structure_bundle: StructureBundle::new(
+                grid_position,
+                CollisionEffectCooldown::EVERY_SECOND,
+            ),
+            direction,
+            inserter,
+        }
+    }
+}
+
+pub fn process_inserters_system(
+    mut inserter_query: Query<(&GridPosition, &Direction, &mut Inserter, &CurrentMapId)>,
+    mut output_inventory_query: Query<&mut OutputInventory>,
+    mut input_inventory_query: Query<&mut InputInventory>,
+    mut fuel_inventory_query: Query<&mut FuelInventory>,
+    mut belt_query: Query<(&Direction, &mut Belt), Without<Inserter>>,
+    mut chest_query: Query<&mut Inventory, With<Chest>>,
+    chunk_query: Query<&StructureLayerManager, With<TilemapChunk>>,
+    multi_map_manager: Res<MultiMapManager>,
+    item_registry: Res<ItemRegistry>,
+) {
+    for (grid_position, &direction, mut inserter, current_map_id) in inserter_query.iter_mut() {
+        let Some(map_manager) = multi_map_manager.maps.get(&current_map_id.0) else {
+            continue;
+        };
+
+        let Some(held) = inserter.held else {
+            // the empty hand comes back above the source before picking again
+            if inserter.swing_progress_ticks > 0 {
+                inserter.swing_progress_ticks -= 1;
+                continue;
+            }
+            let source_tile = tile_in_front(grid_position.0, direction.opposite());
+            let Some(source_entity) = map_manager.get_structure(source_tile, &chunk_query) else {
+                continue;
+            };
+            let picked =
+                if let Ok(mut output_inventory) = output_inventory_query.get_mut(source_entity) {
+                    inserter.take_from_inventory(&mut output_inventory.0)
+                } else if let Ok(mut chest_inventory) = chest_query.get_mut(source_entity) {
+                    inserter.take_from_inventory(&mut chest_inventory)
+                } else if let Ok((_, mut belt)) = belt_query.get_mut(source_entity) {
+                    inserter.take_from_belt(&mut belt)
+                } else {
+                    None
+                };
+            inserter.held = picked;
+            continue;
+        };
+
+        if inserter.swing_progress_ticks < inserter.swing_time_ticks {
+            inserter.swing_progress_ticks += 1;
+            continue;
+        }
+
+        // above the target, the hand waits until everything has been dropped
+        let target_tile = tile_in_front(grid_position.0, direction);
+        let Some(target_entity) = map_manager.get_structure(target_tile, &chunk_query) else {
+            continue;
+        };
+        // fuel goes in the fuel inventory of the target if it has one
+        let fuel_inventory = fuel_inventory_query
+            .get_mut(target_entity)
+            .ok()
+            .filter(|_| item_registry.fuel_value(held.item_type).is_some());
+        let leftover = if let Some(mut fuel_inventory) = fuel_inventory {
+            fuel_inventory.0.insert(held, &item_registry)
+        } else if let Ok(mut input_inventory) = input_inventory_query.get_mut(target_entity) {
+            input_inventory.0.insert(held, &item_registry)
+        } else if let Ok(mut chest_inventory) = chest_query.get_mut(target_entity) {
+            chest_inventory.insert(held, &item_registry)
+        } else if let Ok((&belt_direction, mut belt)) = belt_query.get_mut(target_entity) {
+            drop_on_belt(held, direction, belt_direction, &mut belt)
+        } else {
+            Some(held)
+        };
+        inserter.held = leftover;
+    }
+}
+
+/// puts the items in the middle of the far lane first, returns what didn't fit
+fn drop_on_belt(
+    item_stack: ItemStack,
+    direction: Direction,
+    belt_direction: Direction,
+    belt: &mut Belt,
+) -> Option<ItemStack> {
+    let far_lane = match BeltEntry::new(direction, belt_direction) {
+        Some(BeltEntry::Side(near_lane)) => 1 - near_lane,
+        _ => 0,
+    };
+    let mut quantity_left = item_stack.quantity;
+    for lane in [far_lane, 1 - far_lane] {
+        if quantity_left == 0 {
+            break;
+        }
+        let item = BeltItem {
+            item_type: item_stack.item_type,
+            quality: item_stack.quality,
+            position: Belt::SIDE_ENTRY_POSITION,
+        };
+        if belt.insert(lane, item) {
+            quantity_left -= 1;
+        }
+    }
+    (quantity_left > 0).then_some(ItemStack {
+        quantity: quantity_left,
+        ..item_stack
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::items::Quality;
+
+    #[test]
+    fn test_take_from_inventory() {
+        let mut inserter = Inserter {
+            hand_size: 3,
+            filter: vec![ItemType::IRON_GEAR],
+            ..default()
+        };
+        let mut inventory = Inventory {
+            slots: vec![
+                ItemStack::new(ItemType::IRON_PLATE, Quality::Standard, 5),
+                ItemStack::new(ItemType::IRON_GEAR, Quality::Perfect, 5),
+            ],
+            ..default()
+        };
+        // the filter skips the plates
+        assert_eq!(
+            inserter.take_from_inventory(&mut inventory),
+            Some(ItemStack::new(ItemType::IRON_GEAR, Quality::Perfect, 3))
+        );
+        assert_eq!(inventory.count(ItemType::IRON_GEAR, Quality::Perfect), 2);
+
+        let mut plates_only = Inventory {
+            slots: vec![ItemStack::new(ItemType::IRON_PLATE, Quality::Standard, 5)],
+            ..default()
+        };
+        assert_eq!(inserter.take_from_inventory(&mut plates_only), None);
+
+        // toggled off, the filter is empty and every item is taken
+        inserter.toggle_filter(ItemType::IRON_PLATE);
+        inserter.toggle_filter(ItemType::IRON_PLATE);
+        inserter.toggle_filter(ItemType::IRON_GEAR);
+        assert!(inserter.filter.is_empty());
+        assert_eq!(
+            inserter.take_from_inventory(&mut plates_only),
+            Some(ItemStack::new(ItemType::IRON_PLATE, Quality::Standard, 3))
+        );
+    }
+
+    #[test]
+    fn test_take_from_belt() {
+        let inserter = Inserter {
+            hand_size: 2,
+            ..default()
+        };
+        let mut belt = Belt::default();
+        belt.fill(ItemStack::new(ItemType::IRON_PLATE, Quality::Standard, 3));
+        assert_eq!(
+            inserter.take_from_belt(&mut belt),
+            Some(ItemStack::new(ItemType::IRON_PLATE, Quality::Standard, 2))
+        );
+        assert_eq!(belt.item_count(), 1);
+    }
+}
@@ -2,19 +2,30 @@ use crate::{
     FixedSet, GameSet,
     direction::Direction,
     items::{
-        inventory::{InputInventory, ItemStack, OutputInventory},
+        Quality,
+        inventory::{FuelInventory, InputInventory, Inventory, ItemStack, OutputInventory},
+        production::ProductionStats,
         recipe::{RecipeBook, RecipeId},
+        registry::ItemRegistry,
     },
     loading::LoadingState,
     map::{
-        CurrentMapId, MultiMapManager, StructureLayerManager,
-        coordinates::{TileCoordinates, absolute_coord_to_tile_coord},
-        structure::{BlockSight, StructureBundle},
+        CurrentMapId, MultiMapManager, ResourceNodeLayerManager, StructureLayerManager,
+        coordinates::{GridPosition, TileCoordinates, absolute_coord_to_tile_coord},
+        resource_node::ResourceNode,
+        structure::{
+            BlockSight, StructureBundle,
+            belt::{Belt, BeltEntry, BeltItem},
+            chest::Chest,
+            inserter::Inserter,
+            power::Burner,
+        },
     },
     time::GameTime,
 };
 use bevy::{prelude::*, sprite_render::TilemapChunk};
-use std::f32::consts::{FRAC_PI_2, PI};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 
 pub struct MachinePlugin;
 impl Plugin for MachinePlugin {
@@ -23,13 +34,12 @@ impl Plugin for MachinePlugin {
             .add_systems(
                 FixedUpdate,
                 (
+                    burn_machines_fuel_system,
                     (
                         process_crafting_machines_system,
-                        process_belt_machines_system,
                         process_mining_machines_system,
                     ),
                     transfert_items_to_next_machine_system,
-                    // print_machine_inventories_system,
                 )
                     .chain()
                     .in_set(FixedSet::Process)
@@ -38,14 +48,34 @@ impl Plugin for MachinePlugin {
     }
 }
 
-#[derive(Component)]
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
 pub struct Machine {
     pub action_time_ticks: u64,
+    /// set by the power network of the machine, 0 stops it
     pub action_speed: f32,
     pub action_progress_ticks: u64,
+    /// fraction of tick of progress not counted yet in action_progress_ticks
+    #[serde(default)]
+    pub action_progress_remainder: f32,
 }
 impl Machine {
     pub const DEFAULT_ACTION_TIME_TICKS: u64 = GameTime::TICKS_PER_SECOND as u64 * 1; // 1 second
+
+    /// progresses by action_speed ticks
+    pub fn advance(&mut self) {
+        self.action_progress_remainder += self.action_speed;
+        let ticks = self.action_progress_remainder.floor();
+        self.action_progress_ticks += ticks as u64;
+        self.action_progress_remainder -= ticks;
+    }
+
+    /// uses power or fuel: an action is under way and the machine isn't idle or stalled,
+    /// a machine without power or fuel still asks for it
+    pub fn is_progressing(&self, status: MachineStatus) -> bool {
+        self.action_progress_ticks > 0
+            && self.action_progress_ticks < self.action_time_ticks
+            && matches!(status, MachineStatus::Working | MachineStatus::NoPower)
+    }
 }
 impl Default for Machine {
     fn default() -> Self {
@@ -53,30 +83,137 @@ impl Default for Machine {
             action_time_ticks: Self::DEFAULT_ACTION_TIME_TICKS,
             action_speed: 1.0,
             action_progress_ticks: 0,
+            action_progress_remainder: 0.0,
         }
     }
 }
+/// variant of a machine burning the items of its FuelInventory instead of using power, it stops without fuel
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+pub struct BurnerMachine {
+    /// kW used while working
+    pub consumption: f32,
+    pub burner: Burner,
+}
+impl BurnerMachine {
+    pub const FUEL_SLOTS: u32 = 1;
+
+    pub fn new(consumption: f32) -> Self {
+        Self {
+            consumption,
+            burner: Burner::default(),
+        }
+    }
+
+    /// the machine slows down on its last bit of fuel and stops without it, the fuel of one tick is burnt only while it progresses
+    pub fn burn_fuel(
+        &mut self,
+        machine: &mut Machine,
+        status: MachineStatus,
+        fuel_inventory: &mut FuelInventory,
+        item_registry: &ItemRegistry,
+    ) {
+        let energy = self.consumption / GameTime::TICKS_PER_SECOND as f32;
+        machine.action_speed =
+            (self.burner.available_energy(fuel_inventory, item_registry) / energy).min(1.0);
+        if machine.action_speed > 0.0 && machine.is_progressing(status) {
+            self.burner
+                .burn(energy * machine.action_speed, fuel_inventory, item_registry);
+        }
+    }
+}
+
 #[derive(Bundle)]
 pub struct MachineBaseBundle {
     pub name: Name,
     pub structure_bundle: StructureBundle,
     pub direction: Direction,
     pub machine: Machine,
+    pub status: MachineStatus,
 }
 
-#[derive(Component)]
-pub struct BeltMachine;
-#[derive(Bundle)]
-pub struct BeltMachineBundle {
-    pub base: MachineBaseBundle,
-    pub input_inventory: InputInventory,
-    pub output_inventory: OutputInventory,
-    pub belt_machine: BeltMachine,
+/// why a machine is stopped, checked each tick by the processing systems
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MachineStatus {
+    #[default]
+    Working,
+    NoRecipe,
+    MissingInputs,
+    OutputBlocked,
+    /// no power network or no fuel
+    NoPower,
+    NoResource,
+}
+impl MachineStatus {
+    /// Working while the machine progresses, NoPower if it can't
+    pub fn progressing(machine: &Machine) -> Self {
+        if machine.action_speed > 0.0 {
+            Self::Working
+        } else {
+            Self::NoPower
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Working => "Working",
+            Self::NoRecipe => "No recipe",
+            Self::MissingInputs => "Missing inputs",
+            Self::OutputBlocked => "Output full",
+            Self::NoPower => "No power",
+            Self::NoResource => "No resource",
+        }
+    }
+}
+
+/// recipes list the kinds of machine able to craft them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum MachineKind {
+    #[default]
+    CraftingMachine,
+    /// smelts ores, picks its recipe from its inputs
+    Furnace,
+}
+
+/// chances for a craft to end one quality above or below the average quality of its inputs
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct QualityRoll {
+    pub upgrade_chance: f32,
+    pub downgrade_chance: f32,
+}
+impl QualityRoll {
+    pub fn roll(&self, quality: Quality, rng: &mut impl Rng) -> Quality {
+        let roll: f32 = rng.random();
+        if roll < self.upgrade_chance {
+            quality.upgraded()
+        } else if roll < self.upgrade_chance + self.downgrade_chance {
+            quality.downgraded()
+        } else {
+            quality
+        }
+    }
+}
+impl Default for QualityRoll {
+    fn default() -> Self {
+        Self {
+            upgrade_chance: 0.05,
+            downgrade_chance: 0.1,
+        }
+    }
 }
 
-#[derive(Component)]
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
 pub struct CraftingMachine {
     pub recipe_id: Option<RecipeId>,
+    #[serde(default)]
+    pub kind: MachineKind,
+    #[serde(default)]
+    pub quality_roll: QualityRoll,
+    /// quality of the outputs of the craft in progress, rolled when the inputs are consumed
+    #[serde(default)]
+    pub crafting_quality: Option<Quality>,
+    /// inputs of the craft in progress, given back if the recipe changes before the end
+    #[serde(default)]
+    pub consumed_inputs: Vec<ItemStack>,
 }
 #[derive(Bundle)]
 pub struct CraftingMachineBundle {
@@ -87,22 +224,53 @@ pub struct CraftingMachineBundle {
     pub crafting_machine: CraftingMachine,
 }
 impl CraftingMachine {
+    /// kW used while crafting, from the power network or from fuel
+    pub const POWER_CONSUMPTION: f32 = 75.0;
+    /// kW burnt by a furnace while smelting
+    pub const FURNACE_CONSUMPTION: f32 = 90.0;
+
     pub fn new(recipe_id: RecipeId) -> Self {
         Self {
             recipe_id: Some(recipe_id),
+            ..default()
+        }
+    }
+
+    pub fn furnace() -> Self {
+        Self {
+            kind: MachineKind::Furnace,
+            ..default()
         }
     }
+
+    /// cancels the craft in progress, returns its inputs
+    pub fn change_recipe(
+        &mut self,
+        recipe_id: Option<RecipeId>,
+        machine: &mut Machine,
+    ) -> Vec<ItemStack> {
+        self.recipe_id = recipe_id;
+        self.crafting_quality = None;
+        machine.action_progress_ticks = 0;
+        machine.action_progress_remainder = 0.0;
+        std::mem::take(&mut self.consumed_inputs)
+    }
 }
 impl Default for CraftingMachine {
     fn default() -> Self {
-        Self { recipe_id: None }
+        Self {
+            recipe_id: None,
+            kind: MachineKind::default(),
+            quality_roll: QualityRoll::default(),
+            crafting_quality: None,
+            consumed_inputs: Vec::new(),
+        }
     }
 }
 
-#[derive(Component)]
-pub struct MiningMachine {
-    pub mined_item: Option<ItemStack>,
-}
+/// extracts the resource node under it until it is depleted
+#[derive(Component, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MiningMachine;
 #[derive(Bundle)]
 pub struct MiningMachineBundle {
     pub base: MachineBaseBundle,
@@ -111,134 +279,202 @@ pub struct MiningMachineBundle {
     pub mining_machine: MiningMachine,
 }
 impl MiningMachine {
-    pub fn new(mined_item: ItemStack) -> Self {
-        Self {
-            mined_item: Some(mined_item),
-        }
-    }
-}
-impl Default for MiningMachine {
-    fn default() -> Self {
-        Self { mined_item: None }
-    }
+    /// kW used while mining, from the power network or from fuel
+    pub const POWER_CONSUMPTION: f32 = 90.0;
 }
 
-pub fn process_belt_machines_system(
-    mut machine_query: Query<
-        (&mut Machine, &mut InputInventory, &mut OutputInventory),
-        With<BeltMachine>,
-    >,
+/// idle or stalled burner machines don't burn anything but still need fuel to start
+pub fn burn_machines_fuel_system(
+    mut machine_query: Query<(
+        &mut Machine,
+        &MachineStatus,
+        &mut BurnerMachine,
+        &mut FuelInventory,
+    )>,
+    item_registry: Res<ItemRegistry>,
 ) {
-    for (mut machine, mut input_inventory, mut output_inventory) in machine_query.iter_mut() {
-        if machine.action_progress_ticks >= machine.action_time_ticks {
-            let item_stacks = input_inventory.0.remove_all_item_stack();
-            for item_stack in item_stacks {
-                output_inventory.0.add(item_stack).expect(
-                    "process_belt_machines_system(): transfer to output_inventory didn't work",
-                );
-            }
-            machine.action_progress_ticks = 0;
-        }
-
-        // start if previous action finised and there is items in input_inventory
-        if machine.action_progress_ticks == 0 {
-            if !input_inventory.0.slots.is_empty() {
-                machine.action_time_ticks =
-                    (Machine::DEFAULT_ACTION_TIME_TICKS as f32 / machine.action_speed) as u64;
-                // TODO: see if need to change to 0
-                machine.action_progress_ticks = 1;
-            }
-        } else if machine.action_progress_ticks > 0 {
-            machine.action_progress_ticks += 1;
-        }
+    for (mut machine, status, mut burner_machine, mut fuel_inventory) in machine_query.iter_mut() {
+        burner_machine.burn_fuel(&mut machine, *status, &mut fuel_inventory, &item_registry);
     }
 }
 
 pub fn process_crafting_machines_system(
     mut machine_query: Query<(
         &mut Machine,
-        &CraftingMachine,
+        &mut MachineStatus,
+        &mut CraftingMachine,
         &mut InputInventory,
         &mut OutputInventory,
     )>,
     recipe_book: Res<RecipeBook>,
+    item_registry: Res<ItemRegistry>,
+    mut production_stats: ResMut<ProductionStats>,
 ) {
-    for (mut machine, crafting_machine, mut input_inventory, mut output_inventory) in
-        machine_query.iter_mut()
+    let mut rng = rand::rng();
+    for (
+        mut machine,
+        mut status,
+        mut crafting_machine,
+        mut input_inventory,
+        mut output_inventory,
+    ) in machine_query.iter_mut()
     {
-        let Some(recipe_id) = crafting_machine.recipe_id else {
-            continue;
-        };
-        let Some(recipe) = recipe_book.0.get(&recipe_id) else {
+        // furnaces switch to what they are given once the current recipe has no more inputs
+        if crafting_machine.kind == MachineKind::Furnace && machine.action_progress_ticks == 0 {
+            let current_recipe_available = crafting_machine
+                .recipe_id
+                .and_then(|recipe_id| recipe_book.0.get(&recipe_id))
+                .is_some_and(|recipe| recipe.inputs_available_in(&input_inventory.0));
+            if !current_recipe_available {
+                let next_recipe_id =
+                    recipe_book.recipe_for_inputs(MachineKind::Furnace, &input_inventory.0);
+                // only written on a switch, the machine window watches it
+                if next_recipe_id.is_some() {
+                    crafting_machine.recipe_id = next_recipe_id;
+                }
+            }
+        }
+
+        let Some(recipe) = crafting_machine
+            .recipe_id
+            .and_then(|recipe_id| recipe_book.0.get(&recipe_id))
+            .filter(|recipe| recipe.machines.contains(&crafting_machine.kind))
+        else {
+            // furnaces wait for ores rather than for a recipe
+            status.set_if_neq(if crafting_machine.kind == MachineKind::Furnace {
+                MachineStatus::MissingInputs
+            } else {
+                MachineStatus::NoRecipe
+            });
             continue;
         };
 
-        // use machine.action_time_ticks instead of recipe.base_craft_time_ticks because machine.action_time_ticks change because of machine.action_speed
         if machine.action_progress_ticks >= machine.action_time_ticks {
-            for item_stack in &recipe.outputs {
-                output_inventory
-                    .0
-                    .add(*item_stack)
-                    .expect("add_item_stack() didn't work");
+            let quality = crafting_machine.crafting_quality.unwrap_or_default();
+            let outputs: Vec<_> = recipe
+                .outputs
+                .iter()
+                .map(|output| output.to_item_stack(quality))
+                .collect();
+            // the craft is finished but waits for room in output_inventory
+            if !outputs
+                .iter()
+                .all(|item_stack| output_inventory.0.enough_room(*item_stack, &item_registry))
+            {
+                status.set_if_neq(MachineStatus::OutputBlocked);
+                continue;
+            }
+            for item_stack in outputs {
+                output_inventory.0.insert(item_stack, &item_registry);
+                production_stats.record_produced(item_stack);
+            }
+            // counted once the craft is done, the inputs of a cancelled craft are given back
+            for item_stack in crafting_machine.consumed_inputs.drain(..) {
+                production_stats.record_consumed(item_stack);
             }
+            crafting_machine.crafting_quality = None;
             machine.action_progress_ticks = 0;
         }
 
         // start a new craft if possible
         if machine.action_progress_ticks == 0 {
-            let mut items_present = true;
-            for item_stack in &recipe.inputs {
-                if !input_inventory.0.enough_quantity(*item_stack) {
-                    items_present = false;
-                    break;
-                }
-            }
-            if !items_present {
+            if !recipe.inputs_available_in(&input_inventory.0) {
+                status.set_if_neq(MachineStatus::MissingInputs);
                 continue;
             }
-            // consumes the input items
-            for item_stack in &recipe.inputs {
-                input_inventory.0.remove_quantity(*item_stack);
+            // consumes the input items, the outputs get their average quality give or take a roll
+            let mut consumed_item_stacks = Vec::new();
+            for input in &recipe.inputs {
+                consumed_item_stacks.extend(input.take_from(&mut input_inventory.0));
             }
+            let quality = crafting_machine
+                .quality_roll
+                .roll(Quality::average(&consumed_item_stacks), &mut rng);
+            crafting_machine.crafting_quality = Some(quality);
+            crafting_machine.consumed_inputs = consumed_item_stacks;
 
-            // reset the crafting machine
-            machine.action_time_ticks =
-                (recipe.base_craft_time_ticks as f32 / machine.action_speed) as u64;
+            // reset the crafting machine, machine.action_speed is applied while it progresses
+            machine.action_time_ticks = recipe.base_craft_time_ticks;
             // TODO: see if need to change to 0
             machine.action_progress_ticks = 1;
+            status.set_if_neq(MachineStatus::progressing(&machine));
         } else if machine.action_progress_ticks > 0 {
-            machine.action_progress_ticks += 1;
+            machine.advance();
+            status.set_if_neq(MachineStatus::progressing(&machine));
         }
     }
 }
 
+/// extracts the resource node at the machine position, the node is despawned once depleted
 pub fn process_mining_machines_system(
-    mut machine_query: Query<(&mut Machine, &MiningMachine, &mut OutputInventory)>,
+    mut commands: Commands,
+    mut machine_query: Query<
+        (
+            &mut Machine,
+            &mut MachineStatus,
+            &GridPosition,
+            &CurrentMapId,
+            &mut OutputInventory,
+        ),
+        With<MiningMachine>,
+    >,
+    mut resource_node_query: Query<&mut ResourceNode>,
+    mut chunk_query: Query<&mut ResourceNodeLayerManager, With<TilemapChunk>>,
+    multi_map_manager: Res<MultiMapManager>,
+    item_registry: Res<ItemRegistry>,
+    mut production_stats: ResMut<ProductionStats>,
 ) {
-    for (mut machine, mining_machine, mut output_inventory) in machine_query.iter_mut() {
-        let Some(mined_item) = mining_machine.mined_item else {
+    for (mut machine, mut status, grid_position, current_map_id, mut output_inventory) in
+        machine_query.iter_mut()
+    {
+        let Some(map_manager) = multi_map_manager.maps.get(&current_map_id.0) else {
+            continue;
+        };
+        let Some(resource_node_entity) =
+            map_manager.get_resource_node(grid_position.0, &chunk_query.as_readonly())
+        else {
+            machine.action_progress_ticks = 0;
+            status.set_if_neq(MachineStatus::NoResource);
+            continue;
+        };
+        let Ok(mut resource_node) = resource_node_query.get_mut(resource_node_entity) else {
+            continue;
+        };
+        let Some(mined_item) = resource_node.peek(resource_node.mined_item.quantity) else {
+            status.set_if_neq(MachineStatus::NoResource);
             continue;
         };
 
         if machine.action_progress_ticks >= machine.action_time_ticks {
-            let new_item_stack = mined_item.clone();
-            output_inventory.0.add(new_item_stack).expect(
-                "process_mining_machines_system(): transfer to output_inventory didn't work",
-            );
+            // the mined item waits for room in output_inventory
+            if output_inventory.0.add(mined_item, &item_registry).is_err() {
+                status.set_if_neq(MachineStatus::OutputBlocked);
+                continue;
+            }
+            resource_node.extract(mined_item.quantity);
+            production_stats.record_produced(mined_item);
             machine.action_progress_ticks = 0;
+            if resource_node.is_depleted() {
+                map_manager.remove_resource_node(grid_position.0, &mut chunk_query);
+                commands.entity(resource_node_entity).despawn();
+                status.set_if_neq(MachineStatus::NoResource);
+                continue;
+            }
         }
 
         // start if previous action finised and if there is still room for more items
-        if let Some(mined_item) = mining_machine.mined_item {
-            if machine.action_progress_ticks == 0 && output_inventory.0.enough_room(mined_item) {
-                machine.action_time_ticks =
-                    (Machine::DEFAULT_ACTION_TIME_TICKS as f32 / machine.action_speed) as u64;
-                // TODO: see if need to change to 0
-                machine.action_progress_ticks = 1;
-            } else if machine.action_progress_ticks > 0 {
-                machine.action_progress_ticks += 1;
+        if machine.action_progress_ticks == 0 {
+            if !output_inventory.0.enough_room(mined_item, &item_registry) {
+                status.set_if_neq(MachineStatus::OutputBlocked);
+                continue;
             }
+            machine.action_time_ticks = Machine::DEFAULT_ACTION_TIME_TICKS;
+            // TODO: see if need to change to 0
+            machine.action_progress_ticks = 1;
+        } else {
+            machine.advance();
         }
+        status.set_if_neq(MachineStatus::progressing(&machine));
     }
 }
 
@@ -252,10 +488,15 @@ pub fn transfert_items_to_next_machine_system(
         &mut OutputInventory,
         &CurrentMapId,
     )>,
+    mut belt_query: Query<(&Direction, &mut Belt)>,
+    mut chest_query: Query<&mut Inventory, With<Chest>>,
     chunk_query: Query<&StructureLayerManager, With<TilemapChunk>>,
     multi_map_manager: Res<MultiMapManager>,
+    item_registry: Res<ItemRegistry>,
 ) {
-    // we find all transfer pairs
+    // we find all transfer pairs, machines in front of a belt or a chest put their items in it
+    let mut belt_pairs = Vec::new();
+    let mut chest_pairs = Vec::new();
     let mut transfer_pairs = Vec::new();
     for (source_machine_entity, transform, _, direction, _, _, current_map_id) in
         machine_query.iter()
@@ -276,6 +517,10 @@ pub fn transfert_items_to_next_machine_system(
                 machine_query.get(structure_entity)
             {
                 transfer_pairs.push((source_machine_entity, target_machine_entity))
+            } else if belt_query.contains(structure_entity) {
+                belt_pairs.push((source_machine_entity, structure_entity))
+            } else if chest_query.contains(structure_entity) {
+                chest_pairs.push((source_machine_entity, structure_entity))
             }
         }
     }
@@ -292,46 +537,191 @@ pub fn transfert_items_to_next_machine_system(
             continue;
         };
 
-        let item_stacks = source_output_inventory.0.remove_all_item_stack();
-        for item_stack in item_stacks {
-            if let Some(target_input_inventory) = &mut target_input_inventory {
-                if !target_input_inventory.0.add(item_stack).is_ok() {
-                    source_output_inventory.0.add(item_stack).expect(
-                        "transfer didn't work and couldn't add items back in source_machine",
-                    );
-                }
+        // what doesn't fit stays in the source machine
+        if let Some(target_input_inventory) = &mut target_input_inventory {
+            source_output_inventory
+                .0
+                .transfer_all_to(&mut target_input_inventory.0, &item_registry);
+        }
+    }
+
+    for (source_entity, chest_entity) in chest_pairs {
+        let Ok((_, _, _, _, _, mut source_output_inventory, _)) =
+            machine_query.get_mut(source_entity)
+        else {
+            continue;
+        };
+        let Ok(mut chest_inventory) = chest_query.get_mut(chest_entity) else {
+            continue;
+        };
+        source_output_inventory
+            .0
+            .transfer_all_to(&mut chest_inventory, &item_registry);
+    }
+
+    // at most one item per lane and per tick, the spacing of the belt limits the throughput
+    for (source_entity, belt_entity) in belt_pairs {
+        let Ok((_, _, _, &direction, _, mut source_output_inventory, _)) =
+            machine_query.get_mut(source_entity)
+        else {
+            continue;
+        };
+        let Ok((&belt_direction, mut belt)) = belt_query.get_mut(belt_entity) else {
+            continue;
+        };
+        let Some(entry) = BeltEntry::new(direction, belt_direction) else {
+            continue;
+        };
+        let lanes = match entry {
+            BeltEntry::Back => vec![0, 1],
+            BeltEntry::Side(lane) => vec![lane],
+        };
+        for lane in lanes {
+            let Some(&item_stack) = source_output_inventory.0.slots.first() else {
+                break;
+            };
+            let (lane, position) = entry.slot(lane, false);
+            let item = BeltItem {
+                item_type: item_stack.item_type,
+                quality: item_stack.quality,
+                position,
+            };
+            if belt.insert(lane, item) {
+                source_output_inventory.0.remove(ItemStack::new(
+                    item_stack.item_type,
+                    item_stack.quality,
+                    1,
+                ));
             }
         }
     }
 }
 
-pub fn print_machine_inventories_system(
-    query: Query<(&Name, Option<&InputInventory>, &mut OutputInventory), With<Machine>>,
+pub fn orient_machines_system(
+    mut query: Query<(&Direction, &mut Transform), Or<(With<Machine>, With<Belt>, With<Inserter>)>>,
 ) {
-    let mut counter = 0;
-    for (name, input_inventory, output_inventory) in query.iter() {
-        counter += 1;
-        if let Some(input_inventory) = &input_inventory {
-            println!(
-                "{:?}: {:?} | {:?}",
-                name, input_inventory.0.slots, output_inventory.0.slots
-            )
-        } else {
-            println!("{:?}: {:?}", name, output_inventory.0.slots)
-        }
+    for (direction, mut transform) in query.iter_mut() {
+        transform.rotation = direction.to_rotation();
     }
-    println!("{} machines", counter);
 }
 
-pub fn orient_machines_system(mut query: Query<(&Direction, &mut Transform), With<Machine>>) {
-    for (direction, mut transform) in query.iter_mut() {
-        let angle = match direction {
-            Direction::North => 0.0,       // up = sprite par défaut
-            Direction::East => -FRAC_PI_2, // right = -90°
-            Direction::South => PI,        // down = 180°
-            Direction::West => FRAC_PI_2,  // left = +90°
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::items::{ItemType, registry::test_coal_fuel};
+    use rand::{SeedableRng, rngs::StdRng};
+
+    #[test]
+    fn test_quality_roll() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let never = QualityRoll {
+            upgrade_chance: 0.0,
+            downgrade_chance: 0.0,
+        };
+        assert_eq!(never.roll(Quality::Standard, &mut rng), Quality::Standard);
+
+        let always_upgrade = QualityRoll {
+            upgrade_chance: 1.0,
+            downgrade_chance: 0.0,
+        };
+        assert_eq!(
+            always_upgrade.roll(Quality::Standard, &mut rng),
+            Quality::Perfect
+        );
+
+        let always_downgrade = QualityRoll {
+            upgrade_chance: 0.0,
+            downgrade_chance: 1.0,
+        };
+        assert_eq!(
+            always_downgrade.roll(Quality::Standard, &mut rng),
+            Quality::Defective
+        );
+    }
+
+    #[test]
+    fn test_machine_advance() {
+        let mut machine = Machine {
+            action_speed: 0.5,
+            action_progress_ticks: 1,
+            ..default()
+        };
+        machine.advance();
+        assert_eq!(machine.action_progress_ticks, 1);
+        machine.advance();
+        assert_eq!(machine.action_progress_ticks, 2);
+
+        // without power the machine doesn't progress
+        machine.action_speed = 0.0;
+        machine.advance();
+        assert_eq!(machine.action_progress_ticks, 2);
+    }
+
+    #[test]
+    fn test_stalled_burner_machine_keeps_its_fuel() {
+        let (item_registry, mut fuel_inventory) = test_coal_fuel(1);
+        let mut burner_machine = BurnerMachine::new(MiningMachine::POWER_CONSUMPTION);
+        // the action is finished but the output is full
+        let mut machine = Machine {
+            action_progress_ticks: Machine::DEFAULT_ACTION_TIME_TICKS,
+            ..default()
+        };
+
+        for _ in 0..100 {
+            burner_machine.burn_fuel(
+                &mut machine,
+                MachineStatus::OutputBlocked,
+                &mut fuel_inventory,
+                &item_registry,
+            );
+        }
+        assert_eq!(fuel_inventory.0.count_any_quality(ItemType::COAL), 1);
+        assert_eq!(burner_machine.burner.remaining_energy, 0.0);
+        assert_eq!(machine.action_speed, 1.0);
+
+        // once the output has room the machine progresses and burns
+        machine.action_progress_ticks = 1;
+        burner_machine.burn_fuel(
+            &mut machine,
+            MachineStatus::Working,
+            &mut fuel_inventory,
+            &item_registry,
+        );
+        assert_eq!(fuel_inventory.0.count_any_quality(ItemType::COAL), 0);
+        assert_eq!(
+            burner_machine.burner.remaining_energy,
+            100.0 - MiningMachine::POWER_CONSUMPTION / GameTime::TICKS_PER_SECOND as f32
+        );
+    }
+
+    #[test]
+    fn test_change_recipe_gives_back_inputs() {
+        let consumed_inputs = vec![ItemStack::new(ItemType::IRON_PLATE, Quality::Standard, 2)];
+        let mut crafting_machine = CraftingMachine {
+            crafting_quality: Some(Quality::Standard),
+            consumed_inputs: consumed_inputs.clone(),
+            ..CraftingMachine::new(RecipeId::IRON_PLATE_TO_IRON_GEAR)
+        };
+        let mut machine = Machine {
+            action_progress_ticks: 10,
+            ..default()
         };
 
-        transform.rotation = Quat::from_rotation_z(angle);
+        let given_back = crafting_machine
+            .change_recipe(Some(RecipeId::COPPER_PLATE_TO_COPPER_WIRE), &mut machine);
+        assert_eq!(given_back, consumed_inputs);
+        assert_eq!(
+            crafting_machine.recipe_id,
+            Some(RecipeId::COPPER_PLATE_TO_COPPER_WIRE)
+        );
+        assert_eq!(crafting_machine.crafting_quality, None);
+        assert_eq!(machine.action_progress_ticks, 0);
+        // nothing more to give back
+        assert!(
+            crafting_machine
+                .change_recipe(None, &mut machine)
+                .is_empty()
+        );
     }
 }
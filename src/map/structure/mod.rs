@@ -1,5 +1,9 @@
+pub mod belt;
+pub mod chest;
+pub mod inserter;
 pub mod machine;
 pub mod portal;
+pub mod power;
 mod structure;
 
 pub use structure::*;
@@ -1,9 +1,11 @@
 use bevy::{prelude::*, sprite_render::TilemapChunk};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     map::{
         CurrentMapId, MapId, MultiMapManager, StructureLayerManager,
         coordinates::{GridPosition, TileCoordinates},
+        generation::{ChunkGenerators, WorldSeed},
         structure::{Structure, StructureBundle},
     },
     physics::{
@@ -14,7 +16,7 @@ use crate::{
     units::{PlayerPath, Unit, pathfinding::RecalculateFlowField},
 };
 
-#[derive(Component)]
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
 pub struct Portal {
     pub destination_map_id: MapId,
     pub destination_tile_pos: TileCoordinates,
@@ -61,6 +63,8 @@ pub fn portal_collision_handler(
         With<Unit>,
     >,
     game_time: Res<GameTime>,
+    world_seed: Res<WorldSeed>,
+    chunk_generators: Res<ChunkGenerators>,
 
     asset_server: Res<AssetServer>,
     mut commands: Commands,
@@ -80,6 +84,8 @@ pub fn portal_collision_handler(
     if let Some(target_portal_entity) = destination_map_manager.spawn_chunk_and_get_structure(
         portal.destination_tile_pos,
         &chunk_query,
+        &world_seed,
+        &chunk_generators,
         &asset_server,
         &mut commands,
         &mut message_recalculate,
@@ -0,0 +1,528 @@
+use crate::{
+    FixedSet,
+    items::{
+        inventory::{FuelInventory, Inventory, ItemStack},
+        registry::ItemRegistry,
+    },
+    loading::LoadingState,
+    map::{
+        CurrentMapId, MapId,
+        coordinates::{GridPosition, TileCoordinates},
+        structure::{
+            StructureBundle,
+            machine::{
+                Machine, MachineStatus, process_crafting_machines_system,
+                process_mining_machines_system,
+            },
+        },
+    },
+    physics::collision_event::CollisionEffectCooldown,
+    time::GameTime,
+};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub struct PowerPlugin;
+impl Plugin for PowerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PowerNetworks>().add_systems(
+            FixedUpdate,
+            (update_power_networks_system, distribute_power_system)
+                .chain()
+                .before(process_crafting_machines_system)
+                .before(process_mining_machines_system)
+                .in_set(FixedSet::Process)
+                .run_if(in_state(LoadingState::Ready)),
+        );
+    }
+}
+
+/// connects to the poles in CONNECTION_RADIUS and powers the structures in SUPPLY_RADIUS
+#[derive(Component, Default)]
+pub struct PowerPole;
+impl PowerPole {
+    /// chebyshev distances in tiles
+    pub const CONNECTION_RADIUS: i32 = 6;
+    pub const SUPPLY_RADIUS: i32 = 2;
+
+    pub fn connects(pole_tile: TileCoordinates, other_pole_tile: TileCoordinates) -> bool {
+        chebyshev_distance(pole_tile, other_pole_tile) <= Self::CONNECTION_RADIUS
+    }
+
+    pub fn supplies(pole_tile: TileCoordinates, tile: TileCoordinates) -> bool {
+        chebyshev_distance(pole_tile, tile) <= Self::SUPPLY_RADIUS
+    }
+}
+#[derive(Bundle)]
+pub struct PowerPoleBundle {
+    pub name: Name,
+    pub structure_bundle: StructureBundle,
+    pub power_pole: PowerPole,
+}
+impl PowerPoleBundle {
+    pub fn new(name: Name, grid_position: GridPosition) -> Self {
+        Self {
+            name,
+            structure_bundle: StructureBundle::new(
+                grid_position,
+                CollisionEffectCooldown::EVERY_SECOND,
+            ),
+            power_pole: PowerPole,
+        }
+    }
+}
+
+fn chebyshev_distance(a: TileCoordinates, b: TileCoordinates) -> i32 {
+    (a.x - b.x).abs().max((a.y - b.y).abs())
+}
+
+/// produces up to max_output kW for the network it is connected to
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Generator {
+    pub max_output: f32,
+}
+
+/// generator whose output follows the daylight
+#[derive(Component, Default)]
+pub struct SolarPanel;
+impl SolarPanel {
+    pub const MAX_OUTPUT: f32 = 60.0;
+
+    /// 0 at night, 1 in the middle of the day, linear at sunrise and sunset
+    pub fn daylight(day_percent: f32) -> f32 {
+        if day_percent < GameTime::PERCENT_SUNRISE_START {
+            0.0
+        } else if day_percent < GameTime::PERCENT_MIDDLE_DAY {
+            (day_percent - GameTime::PERCENT_SUNRISE_START)
+                / (GameTime::PERCENT_MIDDLE_DAY - GameTime::PERCENT_SUNRISE_START)
+        } else if day_percent < GameTime::PERCENT_SUNSET_START {
+            1.0
+        } else if day_percent < GameTime::PERCENT_MIDNIGHT {
+            (GameTime::PERCENT_MIDNIGHT - day_percent)
+                / (GameTime::PERCENT_MIDNIGHT - GameTime::PERCENT_SUNSET_START)
+        } else {
+            0.0
+        }
+    }
+}
+#[derive(Bundle)]
+pub struct SolarPanelBundle {
+    pub name: Name,
+    pub structure_bundle: StructureBundle,
+    pub generator: Generator,
+    pub solar_panel: SolarPanel,
+}
+impl SolarPanelBundle {
+    pub fn new(name: Name, grid_position: GridPosition) -> Self {
+        Self {
+            name,
+            structure_bundle: StructureBundle::new(
+                grid_position,
+                CollisionEffectCooldown::EVERY_SECOND,
+            ),
+            generator: Generator {
+                max_output: SolarPanel::MAX_OUTPUT,
+            },
+            solar_panel: SolarPanel,
+        }
+    }
+}
+
+/// energy of the fuel items burnt by burner generators and burner machines
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Burner {
+    /// kJ left from the last item burnt
+    pub remaining_energy: f32,
+}
+impl Burner {
+    pub fn has_fuel(&self, fuel_inventory: &FuelInventory, item_registry: &ItemRegistry) -> bool {
+        self.remaining_energy > 0.0
+            || fuel_inventory
+                .0
+                .slots
+                .iter()
+                .any(|slot| item_registry.fuel_value(slot.item_type).is_some())
+    }
+
+    /// kJ left in the burner and in the items of fuel_inventory
+    pub fn available_energy(
+        &self,
+        fuel_inventory: &FuelInventory,
+        item_registry: &ItemRegistry,
+    ) -> f32 {
+        self.remaining_energy
+            + fuel_inventory
+                .0
+                .slots
+                .iter()
+                .filter_map(|slot| {
+                    item_registry
+                        .fuel_value(slot.item_type)
+                        .map(|fuel_value| fuel_value * slot.quantity as f32)
+                })
+                .sum::<f32>()
+    }
+
+    /// uses energy kJ, items of fuel_inventory are burnt until it is covered, the rest of the last one is kept
+    pub fn burn(
+        &mut self,
+        energy: f32,
+        fuel_inventory: &mut FuelInventory,
+        item_registry: &ItemRegistry,
+    ) {
+        if energy <= 0.0 {
+            return;
+        }
+        while self.remaining_energy < energy {
+            let fuel = fuel_inventory.0.slots.iter().find_map(|slot| {
+                item_registry
+                    .fuel_value(slot.item_type)
+                    .map(|fuel_value| (*slot, fuel_value))
+            });
+            let Some((slot, fuel_value)) = fuel else {
+                break;
+            };
+            fuel_inventory.0.remove(ItemStack {
+                quantity: 1,
+                ..slot
+            });
+            self.remaining_energy += fuel_value;
+        }
+        self.remaining_energy = (self.remaining_energy - energy).max(0.0);
+    }
+}
+
+/// generator burning the items of its FuelInventory, as much as the network needs
+#[derive(Component, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BurnerGenerator(pub Burner);
+impl BurnerGenerator {
+    pub const MAX_OUTPUT: f32 = 150.0;
+    pub const FUEL_SLOTS: u32 = 1;
+}
+#[derive(Bundle)]
+pub struct BurnerGeneratorBundle {
+    pub name: Name,
+    pub structure_bundle: StructureBundle,
+    pub generator: Generator,
+    pub fuel_inventory: FuelInventory,
+    pub burner_generator: BurnerGenerator,
+}
+impl BurnerGeneratorBundle {
+    pub fn new(
+        name: Name,
+        grid_position: GridPosition,
+        burner_generator: BurnerGenerator,
+        fuel_inventory: Inventory,
+    ) -> Self {
+        Self {
+            name,
+            structure_bundle: StructureBundle::new(
+                grid_position,
+                CollisionEffectCooldown::EVERY_SECOND,
+            ),
+            generator: Generator {
+                max_output: BurnerGenerator::MAX_OUTPUT,
+            },
+            fuel_inventory: FuelInventory(fuel_inventory),
+            burner_generator,
+        }
+    }
+}
+
+/// machine that needs power, its Machine::action_speed is the satisfaction of its network
+#[derive(Component, Debug, Clone, Copy)]
+pub struct PowerConsumer {
+    /// kW used while the machine works
+    pub consumption: f32,
+    /// part of the consumption the network gives, 0 without network
+    pub satisfaction: f32,
+}
+impl PowerConsumer {
+    pub fn new(consumption: f32) -> Self {
+        Self {
+            consumption,
+            satisfaction: 0.0,
+        }
+    }
+
+    /// kW asked to the network this tick, nothing while the machine is idle or stalled
+    pub fn demand(&self, machine: &Machine, status: MachineStatus) -> f32 {
+        if machine.is_progressing(status) {
+            self.consumption
+        } else {
+            0.0
+        }
+    }
+}
+
+/// the generators and consumers linked by a group of connected poles
+#[derive(Debug, Default)]
+pub struct PowerNetwork {
+    pub generators: Vec<Entity>,
+    pub consumers: Vec<Entity>,
+    /// kW the generators could give and kW the working consumers need, updated each tick
+    pub supply: f32,
+    pub demand: f32,
+}
+impl PowerNetwork {
+    /// part of the demand the generators give, brownout below 1
+    pub fn satisfaction(&self) -> f32 {
+        if self.demand <= 0.0 {
+            1.0
+        } else {
+            (self.supply / self.demand).min(1.0)
+        }
+    }
+
+    /// part of the supply the consumers use
+    pub fn load(&self) -> f32 {
+        if self.supply <= 0.0 {
+            0.0
+        } else {
+            (self.demand / self.supply).min(1.0)
+        }
+    }
+}
+
+/// rebuilt when a pole, a generator or a consumer is added or removed
+#[derive(Resource, Default)]
+pub struct PowerNetworks(pub Vec<PowerNetwork>);
+
+/// index of the network of each pole, poles of the same map in CONNECTION_RADIUS of each other are in the same network
+pub fn connect_poles(poles: &[(MapId, TileCoordinates)]) -> Vec<usize> {
+    let mut networks: Vec<Option<usize>> = vec![None; poles.len()];
+    let mut network_count = 0;
+    for start in 0..poles.len() {
+        if networks[start].is_some() {
+            continue;
+        }
+        networks[start] = Some(network_count);
+        let mut to_visit = vec![start];
+        while let Some(index) = to_visit.pop() {
+            let (map_id, tile) = poles[index];
+            for (other_index, (other_map_id, other_tile)) in poles.iter().enumerate() {
+                if networks[other_index].is_none()
+                    && *other_map_id == map_id
+                    && PowerPole::connects(tile, *other_tile)
+                {
+                    networks[other_index] = Some(network_count);
+                    to_visit.push(other_index);
+                }
+            }
+        }
+        network_count += 1;
+    }
+    networks.into_iter().flatten().collect()
+}
+
+pub fn update_power_networks_system(
+    pole_query: Query<(&GridPosition, &CurrentMapId), With<PowerPole>>,
+    generator_query: Query<(Entity, &GridPosition, &CurrentMapId), With<Generator>>,
+    consumer_query: Query<(Entity, &GridPosition, &CurrentMapId), With<PowerConsumer>>,
+    added_query: Query<
+        (),
+        Or<(
+            Added<PowerPole>,
+            Added<Generator>,
+            Added<PowerConsumer>,
+            Changed<CurrentMapId>,
+        )>,
+    >,
+    mut removed_poles: RemovedComponents<PowerPole>,
+    mut removed_generators: RemovedComponents<Generator>,
+    mut removed_consumers: RemovedComponents<PowerConsumer>,
+    mut power_networks: ResMut<PowerNetworks>,
+) {
+    let removed = removed_poles.read().count()
+        + removed_generators.read().count()
+        + removed_consumers.read().count();
+    if added_query.is_empty() && removed == 0 {
+        return;
+    }
+
+    let poles: Vec<(MapId, TileCoordinates)> = pole_query
+        .iter()
+        .map(|(grid_position, current_map_id)| (current_map_id.0, grid_position.0))
+        .collect();
+    let pole_networks = connect_poles(&poles);
+    let network_count = pole_networks.iter().max().map_or(0, |max| max + 1);
+    let mut networks: Vec<PowerNetwork> = (0..network_count).map(|_| default()).collect();
+
+    // a structure in reach of several networks joins the first one
+    let network_of = |grid_position: &GridPosition, current_map_id: &CurrentMapId| {
+        poles
+            .iter()
+            .zip(&pole_networks)
+            .find(|((map_id, pole_tile), _)| {
+                *map_id == current_map_id.0 && PowerPole::supplies(*pole_tile, grid_position.0)
+            })
+            .map(|(_, network)| *network)
+    };
+    for (entity, grid_position, current_map_id) in generator_query.iter() {
+        if let Some(network) = network_of(grid_position, current_map_id) {
+            networks[network].generators.push(entity);
+        }
+    }
+    for (entity, grid_position, current_map_id) in consumer_query.iter() {
+        if let Some(network) = network_of(grid_position, current_map_id) {
+            networks[network].consumers.push(entity);
+        }
+    }
+    power_networks.0 = networks;
+}
+
+/// the consumers of each network share what its generators give, the burner generators only burn what is used
+pub fn distribute_power_system(
+    mut generator_query: Query<(
+        &Generator,
+        Option<&SolarPanel>,
+        Option<(&mut BurnerGenerator, &mut FuelInventory)>,
+    )>,
+    mut consumer_query: Query<(&mut PowerConsumer, &mut Machine, &MachineStatus)>,
+    mut power_networks: ResMut<PowerNetworks>,
+    game_time: Res<GameTime>,
+    item_registry: Res<ItemRegistry>,
+) {
+    // consumers without network have no power
+    for (mut power_consumer, mut machine, _) in consumer_query.iter_mut() {
+        power_consumer.satisfaction = 0.0;
+        machine.action_speed = 0.0;
+    }
+
+    let daylight = SolarPanel::daylight(game_time.get_day_percent());
+    for network in power_networks.0.iter_mut() {
+        network.supply = 0.0;
+        for entity in &network.generators {
+            let Ok((generator, solar_panel, burner)) = generator_query.get_mut(*entity) else {
+                continue;
+            };
+            network.supply += match (solar_panel, burner) {
+                (Some(_), _) => generator.max_output * daylight,
+                // no more than the fuel left can give during this tick
+                (None, Some((burner_generator, fuel_inventory))) => generator.max_output.min(
+                    burner_generator
+                        .0
+                        .available_energy(&fuel_inventory, &item_registry)
+                        * GameTime::TICKS_PER_SECOND as f32,
+                ),
+                (None, None) => generator.max_output,
+            };
+        }
+
+        network.demand = network
+            .consumers
+            .iter()
+            .filter_map(|entity| consumer_query.get(*entity).ok())
+            .map(|(power_consumer, machine, status)| power_consumer.demand(machine, *status))
+            .sum();
+
+        let satisfaction = network.satisfaction();
+        for entity in &network.consumers {
+            let Ok((mut power_consumer, mut machine, _)) = consumer_query.get_mut(*entity) else {
+                continue;
+            };
+            power_consumer.satisfaction = satisfaction;
+            machine.action_speed = satisfaction;
+        }
+
+        // kJ burnt during this tick, shared by the burner generators at the same load
+        let load = network.load();
+        for entity in &network.generators {
+            if let Ok((generator, _, Some((mut burner_generator, mut fuel_inventory)))) =
+                generator_query.get_mut(*entity)
+            {
+                burner_generator.0.burn(
+                    generator.max_output * load / GameTime::TICKS_PER_SECOND as f32,
+                    &mut fuel_inventory,
+                    &item_registry,
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::items::{ItemType, registry::test_coal_fuel};
+
+    #[test]
+    fn test_connect_poles() {
+        let tile = |x, y| TileCoordinates { x, y };
+        let poles = [
+            (MapId(0), tile(0, 0)),
+            (MapId(0), tile(20, 0)),
+            (MapId(0), tile(PowerPole::CONNECTION_RADIUS, 3)),
+            // the same tile on another map isn't connected
+            (MapId(1), tile(0, 0)),
+            // connected through the third pole
+            (MapId(0), tile(PowerPole::CONNECTION_RADIUS * 2, 0)),
+        ];
+        assert_eq!(connect_poles(&poles), vec![0, 1, 0, 2, 0]);
+    }
+
+    #[test]
+    fn test_power_network_satisfaction() {
+        let network = PowerNetwork {
+            supply: 60.0,
+            demand: 120.0,
+            ..default()
+        };
+        assert_eq!(network.satisfaction(), 0.5);
+        assert_eq!(network.load(), 1.0);
+
+        let idle = PowerNetwork {
+            supply: 60.0,
+            ..default()
+        };
+        assert_eq!(idle.satisfaction(), 1.0);
+        assert_eq!(idle.load(), 0.0);
+
+        // a machine waiting for room in its output doesn't take power from the working one
+        let power_consumer = PowerConsumer::new(60.0);
+        let working = Machine {
+            action_progress_ticks: 1,
+            ..default()
+        };
+        let blocked = Machine {
+            action_progress_ticks: Machine::DEFAULT_ACTION_TIME_TICKS,
+            ..default()
+        };
+        let network = PowerNetwork {
+            supply: 60.0,
+            demand: power_consumer.demand(&working, MachineStatus::Working)
+                + power_consumer.demand(&blocked, MachineStatus::OutputBlocked),
+            ..default()
+        };
+        assert_eq!(network.satisfaction(), 1.0);
+        assert_eq!(network.load(), 1.0);
+        // a machine without power still asks for it
+        assert_eq!(
+            power_consumer.demand(&working, MachineStatus::NoPower),
+            60.0
+        );
+
+        assert_eq!(SolarPanel::daylight(0.0), 0.0);
+        assert_eq!(SolarPanel::daylight(0.5), 1.0);
+    }
+
+    #[test]
+    fn test_burner_burns_items_as_needed() {
+        let (item_registry, mut fuel_inventory) = test_coal_fuel(3);
+        let mut burner = Burner::default();
+
+        burner.burn(60.0, &mut fuel_inventory, &item_registry);
+        assert_eq!(burner.remaining_energy, 40.0);
+        assert_eq!(fuel_inventory.0.count_any_quality(ItemType::COAL), 2);
+        // the next item covers what the previous one lacks
+        burner.burn(60.0, &mut fuel_inventory, &item_registry);
+        assert_eq!(burner.remaining_energy, 80.0);
+        assert_eq!(fuel_inventory.0.count_any_quality(ItemType::COAL), 1);
+        assert_eq!(
+            burner.available_energy(&fuel_inventory, &item_registry),
+            180.0
+        );
+        burner.burn(180.0, &mut fuel_inventory, &item_registry);
+        assert!(!burner.has_fuel(&fuel_inventory, &item_registry));
+    }
+}
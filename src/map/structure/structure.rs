@@ -3,6 +3,7 @@ use crate::{
     physics::collision_event::CollisionEffectCooldown,
 };
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
 #[derive(Component)]
 pub struct Wall;
@@ -25,6 +26,42 @@ impl WallBundle {
 #[derive(Component)]
 pub struct BlockSight;
 
+/// structures the player can build, items that place one refer to it in their definition
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum StructureKind {
+    Wall,
+    Belt,
+    Inserter,
+    Chest,
+    CraftingMachine,
+    MiningMachine,
+    BurnerCraftingMachine,
+    BurnerMiningMachine,
+    Furnace,
+    PowerPole,
+    SolarPanel,
+    BurnerGenerator,
+}
+impl StructureKind {
+    /// file in Structure::PATH_PNG_FOLDER
+    pub fn png_file_name(self) -> &'static str {
+        match self {
+            Self::Wall => "wall.png",
+            Self::Belt => "belt_machine.png",
+            Self::Inserter => "default_machine.png",
+            Self::Chest => "chest.png",
+            Self::CraftingMachine => "crafting_machine.png",
+            Self::MiningMachine => "mining_machine.png",
+            Self::BurnerCraftingMachine => "burner_crafting_machine.png",
+            Self::BurnerMiningMachine => "burner_mining_machine.png",
+            Self::Furnace => "crafter.png",
+            Self::PowerPole => "power_pole.png",
+            Self::SolarPanel => "solar_panel.png",
+            Self::BurnerGenerator => "burner_generator.png",
+        }
+    }
+}
+
 #[derive(Component, Default)]
 pub struct Structure;
 impl Structure {
@@ -0,0 +1,138 @@
+use bevy::{
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task},
+};
+use futures_lite::future;
+use std::{fs, io, path::PathBuf, time::SystemTime};
+
+use crate::{
+    save::{WorldSnapshot, add_cached_chunks, save_path, write_atomically},
+    time::GameTime,
+};
+
+#[derive(Resource)]
+pub struct AutosaveSettings {
+    /// in-game time between two autosaves
+    pub interval_ticks: u64,
+    /// number of autosaves kept in saves/, the oldest one gets overwritten
+    pub slots: usize,
+}
+impl Default for AutosaveSettings {
+    fn default() -> Self {
+        Self {
+            interval_ticks: GameTime::TICKS_PER_SECOND * 60 * 5, // 5 minutes
+            slots: 3,
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct AutosaveState {
+    next_autosave_tick: Option<u64>,
+    /// None until the first autosave picks the oldest slot on disk
+    next_slot: Option<usize>,
+    task: Option<Task<io::Result<PathBuf>>>,
+}
+impl AutosaveState {
+    /// before a load rewrites the chunk cache: waits for the autosave reading it,
+    /// the next autosave comes one interval after the loaded game time
+    pub fn restart(&mut self) {
+        if let Some(task) = self.task.take() {
+            log_autosave_result(future::block_on(task));
+        }
+        self.next_autosave_tick = None;
+    }
+}
+
+pub fn autosave_name(slot: usize) -> String {
+    format!("autosave_{}", slot)
+}
+
+/// index of the first missing slot, or of the one modified the longest time ago
+fn oldest_slot(modified_times: &[Option<SystemTime>]) -> usize {
+    modified_times
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, modified_time)| **modified_time)
+        .map_or(0, |(slot, _)| slot)
+}
+
+fn first_slot(slots: usize) -> usize {
+    let modified_times: Vec<_> = (0..slots)
+        .map(|slot| {
+            fs::metadata(save_path(&autosave_name(slot)))
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        })
+        .collect();
+    oldest_slot(&modified_times)
+}
+
+/// captures the world on the main thread, then reads the chunk cache and writes the file on another thread
+pub fn autosave_system(
+    game_time: Res<GameTime>,
+    settings: Res<AutosaveSettings>,
+    mut state: ResMut<AutosaveState>,
+    world_snapshot: WorldSnapshot,
+) {
+    let interval_ticks = settings.interval_ticks.max(1);
+    let next_autosave_tick = match state.next_autosave_tick {
+        // the game time went back, a save has been loaded
+        Some(tick) if tick <= game_time.ticks + interval_ticks => tick,
+        _ => *state
+            .next_autosave_tick
+            .insert(game_time.ticks + interval_ticks),
+    };
+    // waits for the previous autosave to be written
+    if game_time.ticks < next_autosave_tick || state.task.is_some() {
+        return;
+    }
+
+    let slots = settings.slots.max(1);
+    let slot = state.next_slot.unwrap_or_else(|| first_slot(slots)) % slots;
+    state.next_slot = Some((slot + 1) % slots);
+    state.next_autosave_tick = Some(game_time.ticks + interval_ticks);
+
+    let mut save_data = world_snapshot.capture_loaded();
+    let path = save_path(&autosave_name(slot));
+    state.task = Some(AsyncComputeTaskPool::get().spawn(async move {
+        add_cached_chunks(&mut save_data)?;
+        let json = serde_json::to_vec(&save_data).map_err(io::Error::other)?;
+        write_atomically(&path, &json)?;
+        Ok(path)
+    }));
+}
+
+pub fn poll_autosave_task_system(mut state: ResMut<AutosaveState>) {
+    let Some(task) = state.task.as_mut() else {
+        return;
+    };
+    let Some(result) = future::block_on(future::poll_once(task)) else {
+        return;
+    };
+    state.task = None;
+    log_autosave_result(result);
+}
+
+fn log_autosave_result(result: io::Result<PathBuf>) {
+    match result {
+        Ok(path) => info!("Autosaved to {}", path.display()),
+        Err(error) => error!("Couldn't autosave: {}", error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_oldest_slot() {
+        let time = |seconds| Some(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds));
+
+        assert_eq!(oldest_slot(&[time(3), time(1), time(2)]), 1);
+        // a missing autosave is used first
+        assert_eq!(oldest_slot(&[time(3), time(1), None]), 2);
+        assert_eq!(oldest_slot(&[]), 0);
+    }
+}
@@ -0,0 +1,398 @@
+use serde_json::{Value, json};
+use std::{fmt, io};
+
+/// turns the json of a save from one version into the next one
+pub type Migration = fn(&mut Value) -> Result<(), SaveError>;
+
+/// MIGRATIONS[i] turns a save of version i + 1 into a save of version i + 2
+pub const MIGRATIONS: &[Migration] = &[
+    item_types_to_ids,
+    recipe_ids_to_ids,
+    belt_machines_to_belts,
+    finite_resource_nodes,
+];
+pub const CURRENT_SAVE_VERSION: u32 = MIGRATIONS.len() as u32 + 1;
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    /// the save has no version or it isn't a positive integer
+    InvalidVersion,
+    /// the save has been written by a newer build
+    TooNew {
+        version: u32,
+        supported: u32,
+    },
+    /// a migration couldn't understand the save
+    Migration {
+        from_version: u32,
+        reason: String,
+    },
+}
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{}", error),
+            Self::Json(error) => write!(f, "invalid save: {}", error),
+            Self::InvalidVersion => write!(f, "the save has no valid version"),
+            Self::TooNew { version, supported } => write!(
+                f,
+                "the save has version {} but this build only supports up to version {}",
+                version, supported
+            ),
+            Self::Migration {
+                from_version,
+                reason,
+            } => write!(
+                f,
+                "couldn't migrate the save from version {}: {}",
+                from_version, reason
+            ),
+        }
+    }
+}
+impl std::error::Error for SaveError {}
+impl From<io::Error> for SaveError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+impl From<serde_json::Error> for SaveError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Json(error)
+    }
+}
+
+pub fn save_version(save: &Value) -> Result<u32, SaveError> {
+    save.get("version")
+        .and_then(Value::as_u64)
+        .and_then(|version| u32::try_from(version).ok())
+        .filter(|version| *version > 0)
+        .ok_or(SaveError::InvalidVersion)
+}
+
+/// applies every migration needed to bring the save to the last version of the chain
+pub fn migrate(save: &mut Value, migrations: &[Migration]) -> Result<(), SaveError> {
+    let supported = migrations.len() as u32 + 1;
+    let version = save_version(save)?;
+    if version > supported {
+        return Err(SaveError::TooNew { version, supported });
+    }
+
+    for (from_version, migration) in (version..).zip(&migrations[version as usize - 1..]) {
+        migration(save)?;
+        save["version"] = Value::from(from_version + 1);
+    }
+    Ok(())
+}
+
+/// v1 -> v2: ItemType was an enum, items are now referred to by the id of their definition
+fn item_types_to_ids(save: &mut Value) -> Result<(), SaveError> {
+    rename_values(
+        save,
+        "item_type",
+        &[
+            ("IronOre", "iron_ore"),
+            ("CopperOre", "copper_ore"),
+            ("IronPlate", "iron_plate"),
+            ("CopperPlate", "copper_plate"),
+            ("IronGear", "iron_gear"),
+            ("CopperWire", "copper_wire"),
+        ],
+        1,
+    )
+}
+
+/// v2 -> v3: RecipeId was an enum, recipes are now referred to by the id of their definition
+fn recipe_ids_to_ids(save: &mut Value) -> Result<(), SaveError> {
+    rename_values(
+        save,
+        "recipe_id",
+        &[
+            ("IronPlateToIronGear", "iron_plate_to_iron_gear"),
+            ("CopperPlateToCopperWire", "copper_plate_to_copper_wire"),
+        ],
+        2,
+    )
+}
+
+/// v3 -> v4: belt machines became belts, their items are put on the lanes from the front, 4 per lane
+/// the items that don't fit are lost
+fn belt_machines_to_belts(save: &mut Value) -> Result<(), SaveError> {
+    match save {
+        Value::Object(object) => {
+            if let Some(belt_machine) = object.remove("BeltMachine") {
+                object.insert("Belt".to_owned(), belt_machine_to_belt(belt_machine)?);
+            }
+            for value in object.values_mut() {
+                belt_machines_to_belts(value)?;
+            }
+        }
+        Value::Array(values) => {
+            for value in values.iter_mut() {
+                belt_machines_to_belts(value)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn belt_machine_to_belt(belt_machine: Value) -> Result<Value, SaveError> {
+    let error = |reason: &str| SaveError::Migration {
+        from_version: 3,
+        reason: reason.to_owned(),
+    };
+    let base = belt_machine
+        .get("base")
+        .ok_or_else(|| error("belt machine without base"))?;
+
+    let mut lanes = [Vec::new(), Vec::new()];
+    let mut item_count = 0;
+    // the output items were the closest to the next machine
+    for inventory in ["output_inventory", "input_inventory"] {
+        let slots = belt_machine[inventory]["slots"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        for slot in slots {
+            let quantity = slot["quantity"]
+                .as_u64()
+                .ok_or_else(|| error("item stack without quantity"))?;
+            for _ in 0..quantity {
+                if item_count == 8 {
+                    break;
+                }
+                let position = 1.0 - (item_count / 2) as f64 * 0.25;
+                lanes[item_count % 2].insert(
+                    0,
+                    json!({
+                        "item_type": slot["item_type"],
+                        "quality": slot["quality"],
+                        "position": position,
+                    }),
+                );
+                item_count += 1;
+            }
+        }
+    }
+
+    Ok(json!({
+        "name": base["name"],
+        "direction": base["direction"],
+        "belt": { "lanes": lanes },
+    }))
+}
+
+/// v4 -> v5: resource nodes were infinite and mining machines kept a copy of their item
+/// the nodes get FINITE_RESOURCE_NODE_AMOUNT items, mining machines now read the node under them
+fn finite_resource_nodes(save: &mut Value) -> Result<(), SaveError> {
+    const FINITE_RESOURCE_NODE_AMOUNT: u32 = 500;
+    match save {
+        Value::Object(object) => {
+            if let Some(Value::Array(resource_nodes)) = object.get_mut("resource_nodes") {
+                for resource_node in resource_nodes.iter_mut() {
+                    let Some(mined_item) = resource_node.get_mut(1).map(Value::take) else {
+                        return Err(SaveError::Migration {
+                            from_version: 4,
+                            reason: "resource node without item".to_owned(),
+                        });
+                    };
+                    resource_node[1] = json!({
+                        "mined_item": mined_item,
+                        "amount": FINITE_RESOURCE_NODE_AMOUNT,
+                    });
+                }
+            }
+            if let Some(mining_machine) = object.get_mut("mining_machine") {
+                *mining_machine = Value::Null;
+            }
+            for value in object.values_mut() {
+                finite_resource_nodes(value)?;
+            }
+        }
+        Value::Array(values) => {
+            for value in values.iter_mut() {
+                finite_resource_nodes(value)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// renames the string values of every field named key anywhere in the save, unknown values are an error
+fn rename_values(
+    save: &mut Value,
+    key: &str,
+    renames: &[(&str, &str)],
+    from_version: u32,
+) -> Result<(), SaveError> {
+    match save {
+        Value::Object(object) => {
+            for (field, value) in object.iter_mut() {
+                match value {
+                    Value::String(old_value) if field == key => {
+                        let Some((_, new_value)) = renames.iter().find(|(old, _)| old == old_value)
+                        else {
+                            return Err(SaveError::Migration {
+                                from_version,
+                                reason: format!("unknown {} {}", key, old_value),
+                            });
+                        };
+                        *old_value = new_value.to_string();
+                    }
+                    _ => rename_values(value, key, renames, from_version)?,
+                }
+            }
+        }
+        Value::Array(values) => {
+            for value in values.iter_mut() {
+                rename_values(value, key, renames, from_version)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn add_field(save: &mut Value) -> Result<(), SaveError> {
+        save["field"] = json!(1);
+        Ok(())
+    }
+
+    fn double_field(save: &mut Value) -> Result<(), SaveError> {
+        let field = save["field"].as_u64().ok_or(SaveError::Migration {
+            from_version: 2,
+            reason: "missing field".to_owned(),
+        })?;
+        save["field"] = json!(field * 2);
+        Ok(())
+    }
+
+    const TEST_MIGRATIONS: &[Migration] = &[add_field, double_field];
+
+    #[test]
+    fn test_migrate_applies_the_chain_in_order() {
+        let mut save = json!({ "version": 1 });
+        migrate(&mut save, TEST_MIGRATIONS).unwrap();
+        assert_eq!(save, json!({ "version": 3, "field": 2 }));
+
+        // only the missing migrations are applied
+        let mut save = json!({ "version": 2, "field": 5 });
+        migrate(&mut save, TEST_MIGRATIONS).unwrap();
+        assert_eq!(save, json!({ "version": 3, "field": 10 }));
+
+        let mut save = json!({ "version": 3, "field": 5 });
+        migrate(&mut save, TEST_MIGRATIONS).unwrap();
+        assert_eq!(save, json!({ "version": 3, "field": 5 }));
+    }
+
+    #[test]
+    fn test_migrate_rejects_newer_saves() {
+        let mut save = json!({ "version": 4 });
+        assert!(matches!(
+            migrate(&mut save, TEST_MIGRATIONS),
+            Err(SaveError::TooNew {
+                version: 4,
+                supported: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn test_migrate_rejects_invalid_versions() {
+        // old saves used a float version
+        for save in [
+            json!({}),
+            json!({ "version": 0 }),
+            json!({ "version": 1.0 }),
+        ] {
+            let mut save = save;
+            assert!(matches!(
+                migrate(&mut save, TEST_MIGRATIONS),
+                Err(SaveError::InvalidVersion)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_migrate_stops_on_error() {
+        let mut save = json!({ "version": 2 });
+        assert!(matches!(
+            migrate(&mut save, TEST_MIGRATIONS),
+            Err(SaveError::Migration {
+                from_version: 2,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_item_types_to_ids() {
+        let mut save = json!({
+            "version": 1,
+            "maps": [{ "resource_nodes": [[{ "x": 0, "y": 0 }, { "item_type": "CopperOre", "quantity": 3 }]] }],
+            "mined_item": { "item_type": "IronOre" },
+        });
+        migrate(&mut save, &[item_types_to_ids]).unwrap();
+        assert_eq!(
+            save,
+            json!({
+                "version": 2,
+                "maps": [{ "resource_nodes": [[{ "x": 0, "y": 0 }, { "item_type": "copper_ore", "quantity": 3 }]] }],
+                "mined_item": { "item_type": "iron_ore" },
+            })
+        );
+    }
+
+    #[test]
+    fn test_belt_machines_to_belts() {
+        let iron_plates = |quantity| json!({ "item_type": "iron_plate", "quality": "Perfect", "quantity": quantity });
+        let mut save = json!({
+            "version": 3,
+            "structures": [[{ "x": 1, "y": 1 }, { "BeltMachine": {
+                "base": { "name": "Belt machine", "direction": "North", "machine": {} },
+                "input_inventory": { "slots": [iron_plates(2)] },
+                "output_inventory": { "slots": [iron_plates(1)] },
+            } }]],
+        });
+        belt_machines_to_belts(&mut save).unwrap();
+
+        let item = |position| json!({ "item_type": "iron_plate", "quality": "Perfect", "position": position });
+        assert_eq!(
+            save["structures"][0][1],
+            json!({ "Belt": {
+                "name": "Belt machine",
+                "direction": "North",
+                "belt": { "lanes": [[item(0.75), item(1.0)], [item(1.0)]] },
+            } })
+        );
+    }
+
+    #[test]
+    fn test_finite_resource_nodes() {
+        let iron_ore = json!({ "item_type": "iron_ore", "quality": "Standard", "quantity": 3 });
+        let mut save = json!({
+            "version": 4,
+            "resource_nodes": [[{ "x": 0, "y": 0 }, iron_ore]],
+            "structures": [[{ "x": 0, "y": 0 }, { "MiningMachine": { "mining_machine": { "mined_item": iron_ore } } }]],
+        });
+        finite_resource_nodes(&mut save).unwrap();
+
+        assert_eq!(
+            save["resource_nodes"][0][1],
+            json!({ "mined_item": iron_ore, "amount": 500 })
+        );
+        assert_eq!(
+            save["structures"][0][1]["MiningMachine"]["mining_machine"],
+            Value::Null
+        );
+    }
+}
@@ -0,0 +1,5 @@
+pub mod autosave;
+pub mod migration;
+mod save;
+
+pub use save::*;
@@ -0,0 +1,243 @@
+use bevy::{ecs::system::SystemParam, prelude::*, sprite_render::TilemapChunk};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    items::production::ProductionStats,
+    loading::LoadingState,
+    map::{
+        DEFAULT_MAP_ID, MapId, MapManager, MultiMapManager,
+        generation::WorldSeed,
+        persistence::{
+            ChunkSave, ChunkSaveQuery, StructureSaveQuery, capture_loaded_chunks,
+            clear_chunk_cache, read_cached_chunks, write_chunk,
+        },
+        resource_node::ResourceNode,
+        structure::Structure,
+    },
+    save::{
+        autosave::{AutosaveSettings, AutosaveState, autosave_system, poll_autosave_task_system},
+        migration::{CURRENT_SAVE_VERSION, MIGRATIONS, SaveError, migrate},
+    },
+    time::GameTime,
+    units::{
+        Unit,
+        pathfinding::{FlowField, RecalculateFlowField},
+        save_units::{UnitSave, UnitSaveQuery},
+    },
+};
+
+pub const PATH_SAVES: &str = "saves";
+pub const QUICKSAVE_NAME: &str = "quicksave";
+
+pub struct SavePlugin;
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AutosaveSettings>()
+            .init_resource::<AutosaveState>()
+            .add_systems(
+                Update,
+                (
+                    save_on_key_system,
+                    load_on_key_system,
+                    poll_autosave_task_system,
+                )
+                    .run_if(in_state(LoadingState::Ready)),
+            )
+            .add_systems(
+                FixedUpdate,
+                autosave_system.run_if(in_state(LoadingState::Ready)),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MapSave {
+    pub map_id: MapId,
+    pub chunks: Vec<ChunkSave>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveData {
+    /// schema version; older saves are migrated when read, see save::migration
+    pub version: u32,
+    pub game_time_ticks: u64,
+    pub world_seed: u64,
+    pub maps: Vec<MapSave>,
+    pub units: Vec<UnitSave>,
+}
+
+pub fn save_path(save_name: &str) -> PathBuf {
+    PathBuf::from(format!("{}/{}.json", PATH_SAVES, save_name))
+}
+
+/// everything read to build a SaveData of the current world
+#[derive(SystemParam)]
+pub struct WorldSnapshot<'w, 's> {
+    game_time: Res<'w, GameTime>,
+    world_seed: Res<'w, WorldSeed>,
+    multi_map_manager: Res<'w, MultiMapManager>,
+    chunk_query: Query<'w, 's, ChunkSaveQuery, With<TilemapChunk>>,
+    structure_query: Query<'w, 's, StructureSaveQuery, With<Structure>>,
+    resource_node_query: Query<'w, 's, &'static ResourceNode>,
+    unit_query: Query<'w, 's, UnitSaveQuery, With<Unit>>,
+}
+impl WorldSnapshot<'_, '_> {
+    pub fn capture(&self) -> io::Result<SaveData> {
+        let mut save_data = self.capture_loaded();
+        add_cached_chunks(&mut save_data)?;
+        Ok(save_data)
+    }
+
+    /// doesn't touch the disk: chunks unloaded on disk must be added with add_cached_chunks()
+    pub fn capture_loaded(&self) -> SaveData {
+        let maps = self
+            .multi_map_manager
+            .maps
+            .iter()
+            .map(|(map_id, map_manager)| MapSave {
+                map_id: *map_id,
+                chunks: capture_loaded_chunks(
+                    map_manager,
+                    &self.chunk_query,
+                    &self.structure_query,
+                    &self.resource_node_query,
+                ),
+            })
+            .collect();
+
+        SaveData {
+            version: CURRENT_SAVE_VERSION,
+            game_time_ticks: self.game_time.ticks,
+            world_seed: self.world_seed.0,
+            maps,
+            units: self.unit_query.iter().map(UnitSave::capture).collect(),
+        }
+    }
+}
+
+/// adds the chunks of the chunk cache that aren't already in the save
+pub fn add_cached_chunks(save_data: &mut SaveData) -> io::Result<()> {
+    for map_save in save_data.maps.iter_mut() {
+        let loaded_chunks: HashSet<_> = map_save
+            .chunks
+            .iter()
+            .map(|chunk_save| chunk_save.chunk_coord)
+            .collect();
+        map_save
+            .chunks
+            .extend(read_cached_chunks(map_save.map_id, &loaded_chunks)?);
+    }
+    Ok(())
+}
+
+/// writes to a temporary file first so a crash never leaves a half written file at path
+pub fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut temporary_path = path.as_os_str().to_owned();
+    temporary_path.push(".tmp");
+    fs::write(&temporary_path, contents)?;
+    fs::rename(&temporary_path, path)
+}
+
+pub fn write_save(save_name: &str, save_data: &SaveData) -> io::Result<()> {
+    let json = serde_json::to_vec(save_data).map_err(io::Error::other)?;
+    write_atomically(&save_path(save_name), &json)
+}
+
+pub fn read_save(save_name: &str) -> Result<SaveData, SaveError> {
+    let json = fs::read_to_string(save_path(save_name))?;
+    let mut save: Value = serde_json::from_str(&json)?;
+    migrate(&mut save, MIGRATIONS)?;
+    Ok(serde_json::from_value(save)?)
+}
+
+pub fn save_on_key_system(input: Res<ButtonInput<KeyCode>>, world_snapshot: WorldSnapshot) {
+    if !input.just_pressed(KeyCode::F5) {
+        return;
+    }
+
+    match world_snapshot
+        .capture()
+        .and_then(|save_data| write_save(QUICKSAVE_NAME, &save_data))
+    {
+        Ok(()) => info!("Saved to {}", save_path(QUICKSAVE_NAME).display()),
+        Err(error) => error!("Couldn't save: {}", error),
+    }
+}
+
+pub fn load_on_key_system(
+    input: Res<ButtonInput<KeyCode>>,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut game_time: ResMut<GameTime>,
+    mut world_seed: ResMut<WorldSeed>,
+    mut multi_map_manager: ResMut<MultiMapManager>,
+    mut flow_field: ResMut<FlowField>,
+    mut production_stats: ResMut<ProductionStats>,
+    mut autosave_state: ResMut<AutosaveState>,
+    unit_query: Query<Entity, With<Unit>>,
+    mut message_recalculate: MessageWriter<RecalculateFlowField>,
+) {
+    if !input.just_pressed(KeyCode::F9) {
+        return;
+    }
+
+    let save_data = match read_save(QUICKSAVE_NAME) {
+        Ok(save_data) => save_data,
+        Err(error) => {
+            error!("Couldn't load: {}", error);
+            return;
+        }
+    };
+
+    autosave_state.restart();
+
+    // removes the current world
+    for unit_entity in unit_query.iter() {
+        commands.entity(unit_entity).despawn();
+    }
+    for (_, map_manager) in multi_map_manager.maps.drain() {
+        map_manager.despawn(&mut commands);
+    }
+
+    // chunks are put in the chunk cache and get spawned when units come close like any unloaded chunk
+    if let Err(error) = clear_chunk_cache() {
+        error!("Couldn't clear the chunk cache: {}", error);
+    }
+    for map_save in save_data.maps {
+        for chunk_save in map_save.chunks.iter() {
+            if let Err(error) = write_chunk(map_save.map_id, chunk_save) {
+                error!(
+                    "Couldn't restore chunk {:?}: {}",
+                    chunk_save.chunk_coord, error
+                );
+            }
+        }
+        multi_map_manager.maps.insert(
+            map_save.map_id,
+            MapManager::new(map_save.map_id, &mut commands),
+        );
+    }
+    multi_map_manager.spawn_map_and_get_mut(&DEFAULT_MAP_ID, &mut commands);
+
+    game_time.ticks = save_data.game_time_ticks;
+    *world_seed = WorldSeed(save_data.world_seed);
+    // the history of the previous world isn't saved
+    *production_stats = ProductionStats::default();
+
+    for unit_save in save_data.units {
+        unit_save.spawn(&mut commands, &asset_server);
+    }
+
+    flow_field.clear(DEFAULT_MAP_ID);
+    message_recalculate.write_default();
+    info!("Loaded {}", save_path(QUICKSAVE_NAME).display());
+}
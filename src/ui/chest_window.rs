@@ -0,0 +1,219 @@
+use crate::{
+    items::{inventory::Inventory, registry::ItemRegistry},
+    map::{
+        CurrentMapId, MultiMapManager, StructureLayerManager,
+        coordinates::{GridPosition, cursor_tile},
+        structure::chest::Chest,
+    },
+    ui::{BUTTON_BACKGROUND_COLOR, WINDOW_BACKGROUND_COLOR, is_cursor_over_ui},
+    units::{Player, build::Hotbar},
+};
+use bevy::{prelude::*, sprite_render::TilemapChunk};
+
+/// the chest whose window is open, there is at most one
+#[derive(Resource, Default)]
+pub struct OpenedChest(pub Option<Entity>);
+
+#[derive(Component)]
+pub struct ChestWindow;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InventorySide {
+    Chest,
+    Player,
+}
+
+/// clicking it moves the stack of the slot to the other inventory
+#[derive(Component)]
+pub struct InventorySlotButton {
+    pub side: InventorySide,
+    pub index: usize,
+}
+
+/// left click on a chest in reach of the player opens it
+pub fn open_chest_on_click_system(
+    buttons: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    interaction_query: Query<&Interaction>,
+    player_query: Query<(&GridPosition, &CurrentMapId), With<Player>>,
+    chest_query: Query<(), With<Chest>>,
+    chunk_query: Query<&StructureLayerManager, With<TilemapChunk>>,
+    multi_map_manager: Res<MultiMapManager>,
+    hotbar: Res<Hotbar>,
+    mut opened_chest: ResMut<OpenedChest>,
+) {
+    // with an item in hand, the click places it
+    if !buttons.just_pressed(MouseButton::Left)
+        || is_cursor_over_ui(&interaction_query)
+        || hotbar.selected.is_some()
+    {
+        return;
+    }
+    let (Ok(window), Ok((camera, camera_transform)), Ok((player_position, current_map_id))) = (
+        windows.single(),
+        camera_query.single(),
+        player_query.single(),
+    ) else {
+        return;
+    };
+    let Some(tile) = cursor_tile(window, camera, camera_transform) else {
+        return;
+    };
+    if !Player::can_reach(player_position.0, tile) {
+        return;
+    }
+    let Some(map_manager) = multi_map_manager.maps.get(&current_map_id.0) else {
+        return;
+    };
+    let chest_entity = map_manager
+        .get_structure(tile, &chunk_query)
+        .filter(|structure_entity| chest_query.contains(*structure_entity));
+    if chest_entity.is_some() {
+        opened_chest.0 = chest_entity;
+    }
+}
+
+/// Escape closes the window, so does walking away from the chest or the chest disappearing
+pub fn close_chest_window_system(
+    input: Res<ButtonInput<KeyCode>>,
+    player_query: Query<&GridPosition, With<Player>>,
+    chest_query: Query<&GridPosition, With<Chest>>,
+    mut opened_chest: ResMut<OpenedChest>,
+) {
+    let Some(chest_entity) = opened_chest.0 else {
+        return;
+    };
+    let in_reach = match (player_query.single(), chest_query.get(chest_entity)) {
+        (Ok(player_position), Ok(chest_position)) => {
+            Player::can_reach(player_position.0, chest_position.0)
+        }
+        _ => false,
+    };
+    if input.just_pressed(KeyCode::Escape) || !in_reach {
+        opened_chest.0 = None;
+    }
+}
+
+pub fn chest_window_slot_button_system(
+    button_query: Query<(&Interaction, &InventorySlotButton), Changed<Interaction>>,
+    mut player_query: Query<&mut Inventory, (With<Player>, Without<Chest>)>,
+    mut chest_query: Query<&mut Inventory, (With<Chest>, Without<Player>)>,
+    opened_chest: Res<OpenedChest>,
+    item_registry: Res<ItemRegistry>,
+) {
+    let Some(chest_entity) = opened_chest.0 else {
+        return;
+    };
+    let (Ok(mut player_inventory), Ok(mut chest_inventory)) =
+        (player_query.single_mut(), chest_query.get_mut(chest_entity))
+    else {
+        return;
+    };
+
+    for (interaction, slot_button) in button_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        // what doesn't fit stays in its slot
+        match slot_button.side {
+            InventorySide::Chest => chest_inventory.transfer_slot_to(
+                slot_button.index,
+                &mut player_inventory,
+                &item_registry,
+            ),
+            InventorySide::Player => player_inventory.transfer_slot_to(
+                slot_button.index,
+                &mut chest_inventory,
+                &item_registry,
+            ),
+        };
+    }
+}
+
+/// rebuilds the window when it is opened or closed and each time one of the two inventories changes
+pub fn update_chest_window_system(
+    mut commands: Commands,
+    window_query: Query<Entity, With<ChestWindow>>,
+    player_query: Query<Ref<Inventory>, (With<Player>, Without<Chest>)>,
+    chest_query: Query<(&Name, Ref<Inventory>), (With<Chest>, Without<Player>)>,
+    opened_chest: Res<OpenedChest>,
+    item_registry: Res<ItemRegistry>,
+) {
+    let chest = opened_chest
+        .0
+        .and_then(|entity| chest_query.get(entity).ok());
+    let player_inventory = player_query.single().ok();
+    let inventories_changed = chest
+        .as_ref()
+        .is_some_and(|(_, inventory)| inventory.is_changed())
+        || player_inventory
+            .as_ref()
+            .is_some_and(|inventory| inventory.is_changed());
+    if !opened_chest.is_changed() && !inventories_changed {
+        return;
+    }
+
+    for window_entity in window_query.iter() {
+        commands.entity(window_entity).despawn();
+    }
+    let (Some((chest_name, chest_inventory)), Some(player_inventory)) = (chest, player_inventory)
+    else {
+        return;
+    };
+
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                right: Val::Px(12.0),
+                top: Val::Px(12.0),
+                flex_direction: FlexDirection::Row,
+                column_gap: Val::Px(12.0),
+                padding: UiRect::all(Val::Px(8.0)),
+                ..default()
+            },
+            BackgroundColor(WINDOW_BACKGROUND_COLOR),
+            ZIndex(200),
+            ChestWindow,
+        ))
+        .with_children(|window| {
+            for (title, inventory, side) in [
+                (chest_name.as_str(), &*chest_inventory, InventorySide::Chest),
+                ("Player", &*player_inventory, InventorySide::Player),
+            ] {
+                window
+                    .spawn(Node {
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(2.0),
+                        ..default()
+                    })
+                    .with_children(|column| {
+                        column.spawn(Text::new(format!(
+                            "{} ({}/{})",
+                            title,
+                            inventory.slots.len(),
+                            inventory.slots_quantity_limit
+                        )));
+                        for (index, slot) in inventory.slots.iter().enumerate() {
+                            let name = item_registry
+                                .get(slot.item_type)
+                                .map_or(slot.item_type.id(), |definition| definition.name.as_str());
+                            column.spawn((
+                                Button,
+                                Node {
+                                    padding: UiRect::horizontal(Val::Px(4.0)),
+                                    ..default()
+                                },
+                                BackgroundColor(BUTTON_BACKGROUND_COLOR),
+                                InventorySlotButton { side, index },
+                                children![Text::new(format!(
+                                    "{} x{} ({:?})",
+                                    name, slot.quantity, slot.quality
+                                ))],
+                            ));
+                        }
+                    });
+            }
+        });
+}
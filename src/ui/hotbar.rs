@@ -0,0 +1,96 @@
+use crate::{
+    items::{inventory::Inventory, registry::ItemRegistry},
+    ui::WINDOW_BACKGROUND_COLOR,
+    units::{
+        Player,
+        build::{HOTBAR_SLOTS, Hotbar, hotbar_items},
+    },
+};
+use bevy::prelude::*;
+
+#[derive(Component)]
+pub struct HotbarWindow;
+
+const SELECTED_SLOT_COLOR: Color = Color::srgb(0.6, 0.5, 0.1);
+const SLOT_COLOR: Color = Color::srgb(0.25, 0.25, 0.25);
+
+/// rebuilds the hotbar at the bottom of the screen when the selection or the player inventory changes
+pub fn update_hotbar_window_system(
+    mut commands: Commands,
+    window_query: Query<Entity, With<HotbarWindow>>,
+    player_query: Query<Ref<Inventory>, With<Player>>,
+    hotbar: Res<Hotbar>,
+    item_registry: Res<ItemRegistry>,
+) {
+    let Ok(inventory) = player_query.single() else {
+        return;
+    };
+    if !hotbar.is_changed() && !inventory.is_changed() && !window_query.is_empty() {
+        return;
+    }
+    for window_entity in window_query.iter() {
+        commands.entity(window_entity).despawn();
+    }
+
+    let items = hotbar_items(&inventory, &item_registry);
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(12.0),
+                width: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            HotbarWindow,
+        ))
+        .with_children(|window| {
+            window
+                .spawn((
+                    Node {
+                        column_gap: Val::Px(4.0),
+                        padding: UiRect::all(Val::Px(4.0)),
+                        ..default()
+                    },
+                    BackgroundColor(WINDOW_BACKGROUND_COLOR),
+                ))
+                .with_children(|bar| {
+                    for index in 0..HOTBAR_SLOTS {
+                        let text = match items.get(index) {
+                            Some((item_type, _)) => format!(
+                                "{}: {} x{}",
+                                (index + 1) % 10,
+                                item_registry
+                                    .get(*item_type)
+                                    .map_or(item_type.id(), |definition| definition.name.as_str()),
+                                inventory.count_any_quality(*item_type)
+                            ),
+                            None => format!("{}:", (index + 1) % 10),
+                        };
+                        let is_selected = items
+                            .get(index)
+                            .is_some_and(|(item_type, _)| hotbar.selected == Some(*item_type));
+                        bar.spawn((
+                            Node {
+                                min_width: Val::Px(48.0),
+                                padding: UiRect::horizontal(Val::Px(4.0)),
+                                ..default()
+                            },
+                            BackgroundColor(if is_selected {
+                                SELECTED_SLOT_COLOR
+                            } else {
+                                SLOT_COLOR
+                            }),
+                            children![(Text::new(text), TextFont::from_font_size(12.0))],
+                        ));
+                    }
+                    bar.spawn((
+                        Text::new(format!(
+                            "R: {:?}  X: Deconstruct  F: Mine  P: Production",
+                            hotbar.direction
+                        )),
+                        TextFont::from_font_size(12.0),
+                    ));
+                });
+        });
+}
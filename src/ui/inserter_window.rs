@@ -0,0 +1,191 @@
+use crate::{
+    items::{ItemType, registry::ItemRegistry},
+    map::{
+        CurrentMapId, MultiMapManager, StructureLayerManager,
+        coordinates::{GridPosition, cursor_tile},
+        structure::inserter::Inserter,
+    },
+    ui::{BUTTON_BACKGROUND_COLOR, WINDOW_BACKGROUND_COLOR, is_cursor_over_ui},
+    units::{Player, build::Hotbar},
+};
+use bevy::{prelude::*, sprite_render::TilemapChunk};
+
+/// the inserter whose window is open, there is at most one
+#[derive(Resource, Default)]
+pub struct OpenedInserter(pub Option<Entity>);
+
+/// remembers the filter it shows, to be rebuilt when it changes
+#[derive(Component)]
+pub struct InserterWindow {
+    pub filter: Vec<ItemType>,
+}
+
+/// clicking it adds the item to the filter of the opened inserter or removes it, None clears the filter
+#[derive(Component)]
+pub struct FilterButton(pub Option<ItemType>);
+
+const SELECTED_FILTER_COLOR: Color = Color::srgb(0.6, 0.5, 0.1);
+
+/// left click on an inserter in reach of the player opens it
+pub fn open_inserter_on_click_system(
+    buttons: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    interaction_query: Query<&Interaction>,
+    player_query: Query<(&GridPosition, &CurrentMapId), With<Player>>,
+    inserter_query: Query<(), With<Inserter>>,
+    chunk_query: Query<&StructureLayerManager, With<TilemapChunk>>,
+    multi_map_manager: Res<MultiMapManager>,
+    hotbar: Res<Hotbar>,
+    mut opened_inserter: ResMut<OpenedInserter>,
+) {
+    // with an item in hand, the click places it
+    if !buttons.just_pressed(MouseButton::Left)
+        || is_cursor_over_ui(&interaction_query)
+        || hotbar.selected.is_some()
+    {
+        return;
+    }
+    let (Ok(window), Ok((camera, camera_transform)), Ok((player_position, current_map_id))) = (
+        windows.single(),
+        camera_query.single(),
+        player_query.single(),
+    ) else {
+        return;
+    };
+    let Some(tile) = cursor_tile(window, camera, camera_transform) else {
+        return;
+    };
+    if !Player::can_reach(player_position.0, tile) {
+        return;
+    }
+    let Some(map_manager) = multi_map_manager.maps.get(&current_map_id.0) else {
+        return;
+    };
+    let inserter_entity = map_manager
+        .get_structure(tile, &chunk_query)
+        .filter(|structure_entity| inserter_query.contains(*structure_entity));
+    if inserter_entity.is_some() {
+        opened_inserter.0 = inserter_entity;
+    }
+}
+
+/// Escape closes the window, so does walking away from the inserter or the inserter disappearing
+pub fn close_inserter_window_system(
+    input: Res<ButtonInput<KeyCode>>,
+    player_query: Query<&GridPosition, With<Player>>,
+    inserter_query: Query<&GridPosition, With<Inserter>>,
+    mut opened_inserter: ResMut<OpenedInserter>,
+) {
+    let Some(inserter_entity) = opened_inserter.0 else {
+        return;
+    };
+    let in_reach = match (player_query.single(), inserter_query.get(inserter_entity)) {
+        (Ok(player_position), Ok(inserter_position)) => {
+            Player::can_reach(player_position.0, inserter_position.0)
+        }
+        _ => false,
+    };
+    if input.just_pressed(KeyCode::Escape) || !in_reach {
+        opened_inserter.0 = None;
+    }
+}
+
+/// the item held by the inserter is still dropped, the filter applies to the next pick
+pub fn inserter_window_filter_button_system(
+    button_query: Query<(&Interaction, &FilterButton), Changed<Interaction>>,
+    mut inserter_query: Query<&mut Inserter>,
+    opened_inserter: Res<OpenedInserter>,
+) {
+    let Some(mut inserter) = opened_inserter
+        .0
+        .and_then(|entity| inserter_query.get_mut(entity).ok())
+    else {
+        return;
+    };
+
+    for (interaction, filter_button) in button_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match filter_button.0 {
+            None => inserter.filter.clear(),
+            Some(item_type) => inserter.toggle_filter(item_type),
+        }
+    }
+}
+
+/// rebuilds the window when it is opened or closed and when the filter of the inserter changes
+pub fn update_inserter_window_system(
+    mut commands: Commands,
+    window_query: Query<(Entity, &InserterWindow)>,
+    inserter_query: Query<(&Name, &Inserter)>,
+    opened_inserter: Res<OpenedInserter>,
+    item_registry: Res<ItemRegistry>,
+) {
+    let inserter = opened_inserter
+        .0
+        .and_then(|entity| inserter_query.get(entity).ok());
+    let shown_filter = window_query.iter().next().map(|(_, window)| &window.filter);
+    let filter = inserter.map(|(_, inserter)| &inserter.filter);
+    if !opened_inserter.is_changed() && !item_registry.is_changed() && shown_filter == filter {
+        return;
+    }
+
+    for (window_entity, _) in window_query.iter() {
+        commands.entity(window_entity).despawn();
+    }
+    let Some((inserter_name, inserter)) = inserter else {
+        return;
+    };
+
+    let filter_button = |item_type: Option<ItemType>, text: String, selected: bool| {
+        (
+            Button,
+            Node {
+                padding: UiRect::horizontal(Val::Px(4.0)),
+                ..default()
+            },
+            BackgroundColor(if selected {
+                SELECTED_FILTER_COLOR
+            } else {
+                BUTTON_BACKGROUND_COLOR
+            }),
+            FilterButton(item_type),
+            children![(Text::new(text), TextFont::from_font_size(12.0))],
+        )
+    };
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                left: Val::Px(12.0),
+                top: Val::Px(12.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(2.0),
+                padding: UiRect::all(Val::Px(8.0)),
+                ..default()
+            },
+            BackgroundColor(WINDOW_BACKGROUND_COLOR),
+            ZIndex(200),
+            InserterWindow {
+                filter: inserter.filter.clone(),
+            },
+        ))
+        .with_children(|window| {
+            window.spawn(Text::new(inserter_name.as_str()));
+            window.spawn((Text::new("Filter"), TextFont::from_font_size(12.0)));
+            window.spawn(filter_button(
+                None,
+                "Any item".to_owned(),
+                inserter.filter.is_empty(),
+            ));
+            for definition in item_registry.iter() {
+                window.spawn(filter_button(
+                    Some(definition.id),
+                    definition.name.clone(),
+                    inserter.filter.contains(&definition.id),
+                ));
+            }
+        });
+}
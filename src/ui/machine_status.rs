@@ -0,0 +1,140 @@
+use crate::{
+    map::{
+        CurrentMapId, MultiMapManager, StructureLayerManager, coordinates::cursor_tile,
+        structure::machine::MachineStatus,
+    },
+    ui::{WINDOW_BACKGROUND_COLOR, is_cursor_over_ui},
+    units::Player,
+};
+use bevy::{prelude::*, sprite_render::TilemapChunk};
+
+/// icon in the corner of a stalled machine, child of the machine
+#[derive(Component)]
+pub struct MachineStatusIcon;
+impl MachineStatusIcon {
+    pub const PATH_PNG_FOLDER: &str = "ui/machine_status/";
+    const SIZE: f32 = 8.0;
+    /// top right corner of the tile, above the machine
+    const OFFSET: Vec3 = Vec3::new(4.0, 4.0, 1.0);
+
+    /// None while the machine works
+    pub fn png_file_name(status: MachineStatus) -> Option<&'static str> {
+        match status {
+            MachineStatus::Working => None,
+            MachineStatus::NoRecipe => Some("no_recipe.png"),
+            MachineStatus::MissingInputs => Some("missing_inputs.png"),
+            MachineStatus::OutputBlocked => Some("output_blocked.png"),
+            MachineStatus::NoPower => Some("no_power.png"),
+            MachineStatus::NoResource => Some("no_resource.png"),
+        }
+    }
+}
+
+/// name and status of the machine under the cursor
+#[derive(Component)]
+pub struct MachineTooltip;
+
+pub fn spawn_machine_status_icons_system(
+    mut commands: Commands,
+    machine_query: Query<Entity, Added<MachineStatus>>,
+) {
+    for machine_entity in machine_query.iter() {
+        commands.entity(machine_entity).with_child((
+            Sprite {
+                custom_size: Some(Vec2::splat(MachineStatusIcon::SIZE)),
+                ..default()
+            },
+            Transform::from_translation(MachineStatusIcon::OFFSET),
+            Visibility::Hidden,
+            MachineStatusIcon,
+        ));
+    }
+}
+
+/// the icon follows the status and stays upright in the same corner whatever the direction of the machine
+pub fn update_machine_status_icons_system(
+    machine_query: Query<(Ref<MachineStatus>, &Transform, &Children), Without<MachineStatusIcon>>,
+    mut icon_query: Query<(&mut Sprite, &mut Transform, &mut Visibility), With<MachineStatusIcon>>,
+    asset_server: Res<AssetServer>,
+) {
+    for (status, machine_transform, children) in machine_query.iter() {
+        for child in children.iter() {
+            let Ok((mut sprite, mut transform, mut visibility)) = icon_query.get_mut(child) else {
+                continue;
+            };
+            let rotation = machine_transform.rotation.inverse();
+            transform.rotation = rotation;
+            transform.translation = rotation * MachineStatusIcon::OFFSET;
+
+            // also true on the first run after the machine spawned
+            if !status.is_changed() {
+                continue;
+            }
+            match MachineStatusIcon::png_file_name(*status) {
+                Some(file_name) => {
+                    sprite.image = asset_server
+                        .load(MachineStatusIcon::PATH_PNG_FOLDER.to_owned() + file_name);
+                    // inherited, so the fog hides it with the machine
+                    *visibility = Visibility::Inherited;
+                }
+                None => *visibility = Visibility::Hidden,
+            }
+        }
+    }
+}
+
+pub fn update_machine_tooltip_system(
+    mut commands: Commands,
+    windows: Query<&Window>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    player_query: Query<&CurrentMapId, With<Player>>,
+    chunk_query: Query<&StructureLayerManager, With<TilemapChunk>>,
+    machine_query: Query<(&Name, &MachineStatus, &InheritedVisibility)>,
+    mut tooltip_query: Query<(&mut Node, &mut Text, &mut Visibility), With<MachineTooltip>>,
+    interaction_query: Query<&Interaction>,
+    multi_map_manager: Res<MultiMapManager>,
+) {
+    let Ok((mut node, mut text, mut visibility)) = tooltip_query.single_mut() else {
+        commands.spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                padding: UiRect::all(Val::Px(4.0)),
+                ..default()
+            },
+            Text::default(),
+            TextFont::from_font_size(12.0),
+            BackgroundColor(WINDOW_BACKGROUND_COLOR),
+            Visibility::Hidden,
+            MachineTooltip,
+        ));
+        return;
+    };
+
+    let (Ok(window), Ok((camera, camera_transform)), Ok(current_map_id)) = (
+        windows.single(),
+        camera_query.single(),
+        player_query.single(),
+    ) else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+    let hovered_machine = cursor_tile(window, camera, camera_transform)
+        .zip(window.cursor_position())
+        .filter(|_| !is_cursor_over_ui(&interaction_query))
+        .and_then(|(tile, cursor)| {
+            let map_manager = multi_map_manager.maps.get(&current_map_id.0)?;
+            let machine_entity = map_manager.get_structure(tile, &chunk_query)?;
+            let (name, status, inherited_visibility) = machine_query.get(machine_entity).ok()?;
+            // nothing to learn about machines hidden by the fog
+            inherited_visibility.get().then_some((name, status, cursor))
+        });
+    let Some((name, status, cursor)) = hovered_machine else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+
+    node.left = Val::Px(cursor.x + 16.0);
+    node.top = Val::Px(cursor.y + 16.0);
+    text.0 = format!("{}: {}", name, status.description());
+    *visibility = Visibility::Visible;
+}
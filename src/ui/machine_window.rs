@@ -0,0 +1,440 @@
+use crate::{
+    direction::Direction,
+    items::{
+        inventory::{FuelInventory, InputInventory, Inventory, ItemStack, OutputInventory},
+        recipe::{Recipe, RecipeBook, RecipeId, RecipeItemStack},
+        registry::ItemRegistry,
+    },
+    map::{
+        CurrentMapId, MultiMapManager, StructureLayerManager,
+        coordinates::{GridPosition, cursor_tile},
+        structure::machine::{CraftingMachine, Machine, MachineStatus},
+    },
+    time::GameTime,
+    ui::{BUTTON_BACKGROUND_COLOR, WINDOW_BACKGROUND_COLOR, is_cursor_over_ui},
+    units::{Player, build::Hotbar},
+};
+use bevy::{prelude::*, sprite_render::TilemapChunk};
+
+/// the machine whose window is open, there is at most one
+#[derive(Resource, Default)]
+pub struct OpenedMachine(pub Option<Entity>);
+
+/// remembers the recipe it shows, to be rebuilt when it changes
+#[derive(Component)]
+pub struct MachineWindow {
+    pub recipe_id: Option<RecipeId>,
+}
+
+/// filled part of the bar, its width follows the progress of the action
+#[derive(Component)]
+pub struct MachineProgressBar;
+impl MachineProgressBar {
+    const WIDTH: f32 = 120.0;
+    const COLOR: Color = Color::srgb(0.3, 0.7, 0.3);
+}
+
+#[derive(Component)]
+pub struct MachineStatusText;
+
+/// one column per inventory of the machine, with the slots it shows
+#[derive(Component, Default)]
+pub struct MachineInventoriesView {
+    pub shown: Vec<Vec<ItemStack>>,
+}
+
+/// clicking it sets the recipe of the opened machine, None removes it
+#[derive(Component)]
+pub struct RecipeButton(pub Option<RecipeId>);
+
+const SELECTED_RECIPE_COLOR: Color = Color::srgb(0.6, 0.5, 0.1);
+
+/// left click on a machine in reach of the player opens it
+pub fn open_machine_on_click_system(
+    buttons: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    interaction_query: Query<&Interaction>,
+    player_query: Query<(&GridPosition, &CurrentMapId), With<Player>>,
+    machine_query: Query<(), With<Machine>>,
+    chunk_query: Query<&StructureLayerManager, With<TilemapChunk>>,
+    multi_map_manager: Res<MultiMapManager>,
+    hotbar: Res<Hotbar>,
+    mut opened_machine: ResMut<OpenedMachine>,
+) {
+    // with an item in hand, the click places it
+    if !buttons.just_pressed(MouseButton::Left)
+        || is_cursor_over_ui(&interaction_query)
+        || hotbar.selected.is_some()
+    {
+        return;
+    }
+    let (Ok(window), Ok((camera, camera_transform)), Ok((player_position, current_map_id))) = (
+        windows.single(),
+        camera_query.single(),
+        player_query.single(),
+    ) else {
+        return;
+    };
+    let Some(tile) = cursor_tile(window, camera, camera_transform) else {
+        return;
+    };
+    if !Player::can_reach(player_position.0, tile) {
+        return;
+    }
+    let Some(map_manager) = multi_map_manager.maps.get(&current_map_id.0) else {
+        return;
+    };
+    let machine_entity = map_manager
+        .get_structure(tile, &chunk_query)
+        .filter(|structure_entity| machine_query.contains(*structure_entity));
+    if machine_entity.is_some() {
+        opened_machine.0 = machine_entity;
+    }
+}
+
+/// Escape closes the window, so does walking away from the machine or the machine disappearing
+pub fn close_machine_window_system(
+    input: Res<ButtonInput<KeyCode>>,
+    player_query: Query<&GridPosition, With<Player>>,
+    machine_query: Query<&GridPosition, With<Machine>>,
+    mut opened_machine: ResMut<OpenedMachine>,
+) {
+    let Some(machine_entity) = opened_machine.0 else {
+        return;
+    };
+    let in_reach = match (player_query.single(), machine_query.get(machine_entity)) {
+        (Ok(player_position), Ok(machine_position)) => {
+            Player::can_reach(player_position.0, machine_position.0)
+        }
+        _ => false,
+    };
+    if input.just_pressed(KeyCode::Escape) || !in_reach {
+        opened_machine.0 = None;
+    }
+}
+
+/// inputs go back to the machine first then to the player, returns false if some didn't fit
+fn give_back_inputs(
+    item_stacks: &[ItemStack],
+    input_inventory: &mut Inventory,
+    player_inventory: &mut Inventory,
+    item_registry: &ItemRegistry,
+) -> bool {
+    item_stacks.iter().all(|item_stack| {
+        input_inventory
+            .insert(*item_stack, item_registry)
+            .is_none_or(|leftover| player_inventory.insert(leftover, item_registry).is_none())
+    })
+}
+
+/// the craft in progress is cancelled, the recipe doesn't change if its inputs can't be given back
+pub fn machine_window_recipe_button_system(
+    button_query: Query<(&Interaction, &RecipeButton), Changed<Interaction>>,
+    mut machine_query: Query<(&mut Machine, &mut CraftingMachine, &mut InputInventory)>,
+    mut player_query: Query<&mut Inventory, With<Player>>,
+    opened_machine: Res<OpenedMachine>,
+    item_registry: Res<ItemRegistry>,
+) {
+    let Some(machine_entity) = opened_machine.0 else {
+        return;
+    };
+    let (Ok((mut machine, mut crafting_machine, mut input_inventory)), Ok(mut player_inventory)) = (
+        machine_query.get_mut(machine_entity),
+        player_query.single_mut(),
+    ) else {
+        return;
+    };
+
+    for (interaction, recipe_button) in button_query.iter() {
+        if *interaction != Interaction::Pressed || crafting_machine.recipe_id == recipe_button.0 {
+            continue;
+        }
+        if !give_back_inputs(
+            &crafting_machine.consumed_inputs,
+            &mut input_inventory.0.clone(),
+            &mut player_inventory.clone(),
+            &item_registry,
+        ) {
+            info!("Not enough room to give back the inputs of the craft in progress");
+            continue;
+        }
+        let given_back = crafting_machine.change_recipe(recipe_button.0, &mut machine);
+        give_back_inputs(
+            &given_back,
+            &mut input_inventory.0,
+            &mut player_inventory,
+            &item_registry,
+        );
+    }
+}
+
+fn recipe_item_stacks_text(
+    item_stacks: &[RecipeItemStack],
+    item_registry: &ItemRegistry,
+) -> String {
+    item_stacks
+        .iter()
+        .map(|item_stack| {
+            format!(
+                "{} {}",
+                item_stack.quantity,
+                item_registry.name(item_stack.item_type)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" + ")
+}
+
+fn recipe_text(recipe: &Recipe, item_registry: &ItemRegistry) -> String {
+    format!(
+        "{} -> {} ({:.1}s)",
+        recipe_item_stacks_text(&recipe.inputs, item_registry),
+        recipe_item_stacks_text(&recipe.outputs, item_registry),
+        recipe.base_craft_time_ticks as f32 / GameTime::TICKS_PER_SECOND as f32
+    )
+}
+
+/// rebuilds the window when it is opened or closed and when the recipe of the machine changes
+pub fn update_machine_window_system(
+    mut commands: Commands,
+    window_query: Query<(Entity, &MachineWindow)>,
+    machine_query: Query<(&Name, &Direction, Option<&CraftingMachine>), With<Machine>>,
+    opened_machine: Res<OpenedMachine>,
+    recipe_book: Res<RecipeBook>,
+    item_registry: Res<ItemRegistry>,
+) {
+    let machine = opened_machine
+        .0
+        .and_then(|entity| machine_query.get(entity).ok());
+    let shown_recipe_id = window_query
+        .iter()
+        .next()
+        .map(|(_, window)| window.recipe_id);
+    let recipe_id = machine.map(|(_, _, crafting_machine)| {
+        crafting_machine.and_then(|crafting_machine| crafting_machine.recipe_id)
+    });
+    if !opened_machine.is_changed() && !recipe_book.is_changed() && shown_recipe_id == recipe_id {
+        return;
+    }
+
+    for (window_entity, _) in window_query.iter() {
+        commands.entity(window_entity).despawn();
+    }
+    let Some((machine_name, direction, crafting_machine)) = machine else {
+        return;
+    };
+
+    // the recipes the machine can craft, in a stable order
+    let buttons: Vec<_> = crafting_machine
+        .map(|crafting_machine| {
+            let mut recipes: Vec<_> = recipe_book
+                .0
+                .iter()
+                .filter(|(_, recipe)| recipe.machines.contains(&crafting_machine.kind))
+                .collect();
+            recipes.sort_by_key(|(recipe_id, _)| **recipe_id);
+            std::iter::once((None, "None".to_owned()))
+                .chain(recipes.into_iter().map(|(recipe_id, recipe)| {
+                    (Some(*recipe_id), recipe_text(recipe, &item_registry))
+                }))
+                .collect()
+        })
+        .unwrap_or_default();
+    let selected_recipe_id = recipe_id.flatten();
+
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                left: Val::Px(12.0),
+                top: Val::Px(12.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(2.0),
+                padding: UiRect::all(Val::Px(8.0)),
+                ..default()
+            },
+            BackgroundColor(WINDOW_BACKGROUND_COLOR),
+            ZIndex(200),
+            MachineWindow {
+                recipe_id: selected_recipe_id,
+            },
+        ))
+        .with_children(|window| {
+            window.spawn(Text::new(format!("{} ({:?})", machine_name, direction)));
+            window.spawn((
+                Text::default(),
+                TextFont::from_font_size(12.0),
+                MachineStatusText,
+            ));
+            window.spawn((
+                Node {
+                    width: Val::Px(MachineProgressBar::WIDTH),
+                    height: Val::Px(6.0),
+                    ..default()
+                },
+                BackgroundColor(BUTTON_BACKGROUND_COLOR),
+                children![(
+                    Node {
+                        width: Val::Px(0.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    BackgroundColor(MachineProgressBar::COLOR),
+                    MachineProgressBar,
+                )],
+            ));
+            window.spawn((
+                Node {
+                    flex_direction: FlexDirection::Row,
+                    column_gap: Val::Px(12.0),
+                    ..default()
+                },
+                MachineInventoriesView::default(),
+            ));
+            if buttons.is_empty() {
+                return;
+            }
+            window.spawn((Text::new("Recipe"), TextFont::from_font_size(12.0)));
+            for (recipe_id, text) in buttons {
+                window.spawn((
+                    Button,
+                    Node {
+                        padding: UiRect::horizontal(Val::Px(4.0)),
+                        ..default()
+                    },
+                    BackgroundColor(if recipe_id == selected_recipe_id {
+                        SELECTED_RECIPE_COLOR
+                    } else {
+                        BUTTON_BACKGROUND_COLOR
+                    }),
+                    RecipeButton(recipe_id),
+                    children![Text::new(text)],
+                ));
+            }
+        });
+}
+
+/// live part of the window, updated each frame
+pub fn update_machine_window_progress_system(
+    machine_query: Query<(&Machine, &MachineStatus)>,
+    mut progress_bar_query: Query<&mut Node, With<MachineProgressBar>>,
+    mut status_text_query: Query<&mut Text, With<MachineStatusText>>,
+    opened_machine: Res<OpenedMachine>,
+) {
+    let Some((machine, status)) = opened_machine
+        .0
+        .and_then(|entity| machine_query.get(entity).ok())
+    else {
+        return;
+    };
+    for mut node in progress_bar_query.iter_mut() {
+        node.width = Val::Px(MachineProgressBar::WIDTH * machine_progress(machine));
+    }
+    for mut text in status_text_query.iter_mut() {
+        text.0 = status.description().to_owned();
+    }
+}
+
+/// fraction of the action done, 0 while idle
+pub fn machine_progress(machine: &Machine) -> f32 {
+    if machine.action_time_ticks == 0 {
+        return 0.0;
+    }
+    (machine.action_progress_ticks as f32 / machine.action_time_ticks as f32).clamp(0.0, 1.0)
+}
+
+/// rebuilds the inventory columns only when their content differs from what they show
+pub fn update_machine_window_inventories_system(
+    mut commands: Commands,
+    mut view_query: Query<(Entity, &mut MachineInventoriesView)>,
+    machine_query: Query<(
+        Option<&InputInventory>,
+        Option<&FuelInventory>,
+        &OutputInventory,
+    )>,
+    opened_machine: Res<OpenedMachine>,
+    item_registry: Res<ItemRegistry>,
+    asset_server: Res<AssetServer>,
+) {
+    let Some((input_inventory, fuel_inventory, output_inventory)) = opened_machine
+        .0
+        .and_then(|entity| machine_query.get(entity).ok())
+    else {
+        return;
+    };
+    let sections: Vec<(&str, &Inventory)> = [
+        input_inventory.map(|inventory| ("Input", &inventory.0)),
+        fuel_inventory.map(|inventory| ("Fuel", &inventory.0)),
+        Some(("Output", &output_inventory.0)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    let slots: Vec<Vec<ItemStack>> = sections
+        .iter()
+        .map(|(_, inventory)| inventory.slots.clone())
+        .collect();
+
+    for (view_entity, mut view) in view_query.iter_mut() {
+        if view.shown == slots {
+            continue;
+        }
+        view.shown = slots.clone();
+        let mut view_commands = commands.entity(view_entity);
+        view_commands.despawn_related::<Children>();
+        view_commands.with_children(|view| {
+            for (title, inventory) in &sections {
+                view.spawn(Node {
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(2.0),
+                    ..default()
+                })
+                .with_children(|column| {
+                    column.spawn((
+                        Text::new(format!(
+                            "{} ({}/{})",
+                            title,
+                            inventory.slots.len(),
+                            inventory.slots_quantity_limit
+                        )),
+                        TextFont::from_font_size(12.0),
+                    ));
+                    for slot in &inventory.slots {
+                        let icon = item_registry
+                            .get(slot.item_type)
+                            .map_or_else(default, |definition| {
+                                asset_server.load(&definition.sprite)
+                            });
+                        column.spawn((
+                            Node {
+                                column_gap: Val::Px(4.0),
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            children![
+                                (
+                                    ImageNode::new(icon),
+                                    Node {
+                                        width: Val::Px(16.0),
+                                        height: Val::Px(16.0),
+                                        ..default()
+                                    },
+                                ),
+                                (
+                                    Text::new(format!(
+                                        "{} x{} ({:?})",
+                                        item_registry.name(slot.item_type),
+                                        slot.quantity,
+                                        slot.quality
+                                    )),
+                                    TextFont::from_font_size(12.0),
+                                ),
+                            ],
+                        ));
+                    }
+                });
+            }
+        });
+    }
+}
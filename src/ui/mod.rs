@@ -0,0 +1,9 @@
+pub mod chest_window;
+pub mod hotbar;
+pub mod inserter_window;
+pub mod machine_status;
+pub mod machine_window;
+pub mod production_window;
+mod ui;
+
+pub use ui::*;
@@ -0,0 +1,279 @@
+use crate::{
+    items::{
+        production::{
+            PATH_PRODUCTION_CSV, ProductionBucket, ProductionHistory, ProductionPeriod,
+            ProductionStats,
+        },
+        registry::ItemRegistry,
+    },
+    save::write_atomically,
+    time::GameTime,
+    ui::{BUTTON_BACKGROUND_COLOR, WINDOW_BACKGROUND_COLOR},
+};
+use bevy::prelude::*;
+use std::path::Path;
+
+/// P opens the window, it shows the history of one period
+#[derive(Resource, Default)]
+pub struct ProductionView {
+    pub opened: bool,
+    pub period: ProductionPeriod,
+}
+
+#[derive(Component)]
+pub struct ProductionWindow;
+
+/// the graphs, refreshed once per second of game time
+#[derive(Component)]
+pub struct ProductionGraphs {
+    pub shown_second: Option<u64>,
+}
+
+#[derive(Component)]
+pub struct ProductionPeriodButton(pub ProductionPeriod);
+
+#[derive(Component)]
+pub struct ExportCsvButton;
+
+const SELECTED_PERIOD_COLOR: Color = Color::srgb(0.6, 0.5, 0.1);
+const PRODUCED_COLOR: Color = Color::srgb(0.3, 0.7, 0.3);
+const CONSUMED_COLOR: Color = Color::srgb(0.8, 0.3, 0.2);
+const GRAPH_HEIGHT: f32 = 24.0;
+
+pub fn toggle_production_window_system(
+    input: Res<ButtonInput<KeyCode>>,
+    mut production_view: ResMut<ProductionView>,
+) {
+    if input.just_pressed(KeyCode::KeyP) {
+        production_view.opened = !production_view.opened;
+    } else if input.just_pressed(KeyCode::Escape) && production_view.opened {
+        production_view.opened = false;
+    }
+}
+
+pub fn production_window_button_system(
+    period_button_query: Query<(&Interaction, &ProductionPeriodButton), Changed<Interaction>>,
+    export_button_query: Query<&Interaction, (Changed<Interaction>, With<ExportCsvButton>)>,
+    mut production_view: ResMut<ProductionView>,
+    production_stats: Res<ProductionStats>,
+) {
+    for (interaction, period_button) in period_button_query.iter() {
+        if *interaction == Interaction::Pressed && production_view.period != period_button.0 {
+            production_view.period = period_button.0;
+        }
+    }
+    for interaction in export_button_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match write_atomically(
+            Path::new(PATH_PRODUCTION_CSV),
+            production_stats.to_csv().as_bytes(),
+        ) {
+            Ok(()) => info!("Production statistics exported to {}", PATH_PRODUCTION_CSV),
+            Err(error) => error!("Couldn't export the production statistics: {}", error),
+        }
+    }
+}
+
+/// rebuilds the window when it is opened or closed and when the period changes
+pub fn update_production_window_system(
+    mut commands: Commands,
+    window_query: Query<Entity, With<ProductionWindow>>,
+    production_view: Res<ProductionView>,
+) {
+    if !production_view.is_changed() {
+        return;
+    }
+    for window_entity in window_query.iter() {
+        commands.entity(window_entity).despawn();
+    }
+    if !production_view.opened {
+        return;
+    }
+
+    let period_button = |period: ProductionPeriod, text: &str| {
+        (
+            Button,
+            Node {
+                padding: UiRect::horizontal(Val::Px(4.0)),
+                ..default()
+            },
+            BackgroundColor(if period == production_view.period {
+                SELECTED_PERIOD_COLOR
+            } else {
+                BUTTON_BACKGROUND_COLOR
+            }),
+            ProductionPeriodButton(period),
+            children![Text::new(text)],
+        )
+    };
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Percent(25.0),
+            top: Val::Px(12.0),
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(4.0),
+            padding: UiRect::all(Val::Px(8.0)),
+            ..default()
+        },
+        BackgroundColor(WINDOW_BACKGROUND_COLOR),
+        ZIndex(200),
+        ProductionWindow,
+        children![
+            (
+                Node {
+                    column_gap: Val::Px(4.0),
+                    ..default()
+                },
+                children![
+                    Text::new("Production"),
+                    period_button(ProductionPeriod::Minute, "Per minute"),
+                    period_button(ProductionPeriod::Hour, "Per hour"),
+                    (
+                        Button,
+                        Node {
+                            padding: UiRect::horizontal(Val::Px(4.0)),
+                            ..default()
+                        },
+                        BackgroundColor(BUTTON_BACKGROUND_COLOR),
+                        ExportCsvButton,
+                        children![Text::new("Export CSV")],
+                    ),
+                ],
+            ),
+            (
+                Node {
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(2.0),
+                    ..default()
+                },
+                ProductionGraphs { shown_second: None },
+            ),
+        ],
+    ));
+}
+
+/// one line per item: its production and consumption in the last complete bucket and a bar per bucket
+pub fn update_production_graphs_system(
+    mut commands: Commands,
+    mut graphs_query: Query<(Entity, &mut ProductionGraphs)>,
+    production_view: Res<ProductionView>,
+    production_stats: Res<ProductionStats>,
+    game_time: Res<GameTime>,
+    item_registry: Res<ItemRegistry>,
+) {
+    let second = game_time.ticks / GameTime::TICKS_PER_SECOND;
+    let history = production_stats.history(production_view.period);
+    for (graphs_entity, mut graphs) in graphs_query.iter_mut() {
+        if graphs.shown_second == Some(second) {
+            continue;
+        }
+        graphs.shown_second = Some(second);
+
+        let mut graphs_commands = commands.entity(graphs_entity);
+        graphs_commands.despawn_related::<Children>();
+        graphs_commands.with_children(|graphs| {
+            let item_types = history.item_types();
+            if item_types.is_empty() {
+                graphs.spawn((
+                    Text::new("Nothing produced yet"),
+                    TextFont::from_font_size(12.0),
+                ));
+            }
+            for item_type in item_types {
+                let counts = |bucket: &ProductionBucket| {
+                    (
+                        bucket.produced.get(&item_type).copied().unwrap_or(0),
+                        bucket.consumed.get(&item_type).copied().unwrap_or(0),
+                    )
+                };
+                let (produced, consumed) = last_complete_bucket(history, counts);
+                graphs
+                    .spawn(Node {
+                        column_gap: Val::Px(8.0),
+                        align_items: AlignItems::End,
+                        ..default()
+                    })
+                    .with_children(|line| {
+                        line.spawn((
+                            Node {
+                                width: Val::Px(180.0),
+                                ..default()
+                            },
+                            children![(
+                                Text::new(format!(
+                                    "{} +{} -{}",
+                                    item_registry.name(item_type),
+                                    produced,
+                                    consumed
+                                )),
+                                TextFont::from_font_size(12.0),
+                            )],
+                        ));
+                        spawn_bar_graph(line, history, counts);
+                    });
+            }
+        });
+    }
+}
+
+/// the bucket being filled is incomplete, the one before it gives the rate
+fn last_complete_bucket(
+    history: &ProductionHistory,
+    counts: impl Fn(&ProductionBucket) -> (u32, u32),
+) -> (u32, u32) {
+    history.buckets.iter().rev().nth(1).map_or((0, 0), counts)
+}
+
+/// a green bar for the production and a red bar for the consumption of each bucket, oldest on the left
+fn spawn_bar_graph(
+    parent: &mut ChildSpawnerCommands,
+    history: &ProductionHistory,
+    counts: impl Fn(&ProductionBucket) -> (u32, u32),
+) {
+    let bucket_counts: Vec<(u32, u32)> = history.buckets.iter().map(counts).collect();
+    let max_count = bucket_counts
+        .iter()
+        .map(|(produced, consumed)| *produced.max(consumed))
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let bar = |count: u32, color: Color| {
+        (
+            Node {
+                width: Val::Px(1.0),
+                height: Val::Px(GRAPH_HEIGHT * count as f32 / max_count as f32),
+                ..default()
+            },
+            BackgroundColor(color),
+        )
+    };
+
+    parent
+        .spawn((
+            Node {
+                height: Val::Px(GRAPH_HEIGHT),
+                align_items: AlignItems::End,
+                column_gap: Val::Px(1.0),
+                ..default()
+            },
+            BackgroundColor(BUTTON_BACKGROUND_COLOR),
+        ))
+        .with_children(|graph| {
+            // the missing buckets of a young history are empty, the graph keeps its width
+            let missing_buckets = history.max_buckets.saturating_sub(bucket_counts.len());
+            for (produced, consumed) in
+                std::iter::repeat_n((0, 0), missing_buckets).chain(bucket_counts.iter().copied())
+            {
+                graph.spawn((
+                    Node {
+                        align_items: AlignItems::End,
+                        ..default()
+                    },
+                    children![bar(produced, PRODUCED_COLOR), bar(consumed, CONSUMED_COLOR)],
+                ));
+            }
+        });
+}
@@ -0,0 +1,99 @@
+use crate::{
+    GameSet,
+    loading::LoadingState,
+    map::structure::machine::orient_machines_system,
+    ui::chest_window::{
+        OpenedChest, chest_window_slot_button_system, close_chest_window_system,
+        open_chest_on_click_system, update_chest_window_system,
+    },
+    ui::hotbar::update_hotbar_window_system,
+    ui::inserter_window::{
+        OpenedInserter, close_inserter_window_system, inserter_window_filter_button_system,
+        open_inserter_on_click_system, update_inserter_window_system,
+    },
+    ui::machine_status::{
+        spawn_machine_status_icons_system, update_machine_status_icons_system,
+        update_machine_tooltip_system,
+    },
+    ui::machine_window::{
+        OpenedMachine, close_machine_window_system, machine_window_recipe_button_system,
+        open_machine_on_click_system, update_machine_window_inventories_system,
+        update_machine_window_progress_system, update_machine_window_system,
+    },
+    ui::production_window::{
+        ProductionView, production_window_button_system, toggle_production_window_system,
+        update_production_graphs_system, update_production_window_system,
+    },
+};
+use bevy::prelude::*;
+
+pub struct UiPlugin;
+impl Plugin for UiPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<OpenedChest>()
+            .init_resource::<OpenedMachine>()
+            .init_resource::<OpenedInserter>()
+            .init_resource::<ProductionView>()
+            .add_systems(
+                Update,
+                (
+                    (
+                        open_chest_on_click_system,
+                        chest_window_slot_button_system,
+                        close_chest_window_system,
+                        open_machine_on_click_system,
+                        machine_window_recipe_button_system,
+                        close_machine_window_system,
+                        open_inserter_on_click_system,
+                        inserter_window_filter_button_system,
+                        close_inserter_window_system,
+                        toggle_production_window_system,
+                        production_window_button_system,
+                    )
+                        .chain()
+                        .in_set(GameSet::Input),
+                    (
+                        update_chest_window_system,
+                        update_hotbar_window_system,
+                        update_inserter_window_system,
+                        (
+                            update_machine_window_system,
+                            update_machine_window_progress_system,
+                            update_machine_window_inventories_system,
+                        )
+                            .chain(),
+                        (
+                            update_production_window_system,
+                            update_production_graphs_system,
+                        )
+                            .chain(),
+                        update_machine_tooltip_system,
+                    )
+                        .in_set(GameSet::UI),
+                )
+                    .chain()
+                    .run_if(in_state(LoadingState::Ready)),
+            )
+            .add_systems(
+                PostUpdate,
+                (
+                    spawn_machine_status_icons_system,
+                    update_machine_status_icons_system,
+                )
+                    .chain()
+                    .after(orient_machines_system)
+                    .in_set(GameSet::Visual)
+                    .run_if(in_state(LoadingState::Ready)),
+            );
+    }
+}
+
+pub const WINDOW_BACKGROUND_COLOR: Color = Color::srgba(0.1, 0.1, 0.1, 0.85);
+pub const BUTTON_BACKGROUND_COLOR: Color = Color::srgb(0.25, 0.25, 0.25);
+
+/// true if the cursor is over a button, so clicks on the ui don't go to the world below
+pub fn is_cursor_over_ui(interaction_query: &Query<&Interaction>) -> bool {
+    interaction_query
+        .iter()
+        .any(|interaction| *interaction != Interaction::None)
+}
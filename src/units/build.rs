@@ -0,0 +1,425 @@
+use crate::{
+    GameSet,
+    direction::Direction,
+    items::{
+        ItemType, Quality,
+        inventory::{Inventory, ItemStack},
+        registry::ItemRegistry,
+    },
+    loading::LoadingState,
+    map::{
+        CurrentMapId, MapManager, MultiMapManager, StructureLayerManager,
+        coordinates::{
+            GridPosition, TileCoordinates, cursor_tile, tile_coord_to_absolute_coord,
+            tile_coord_to_chunk_coord,
+        },
+        persistence::{StructureSave, StructureSaveQuery},
+        structure::{Structure, StructureKind},
+    },
+    ui::{chest_window::OpenedChest, is_cursor_over_ui},
+    units::{Player, Unit, fov::RecalculateFov, pathfinding::RecalculateFlowField},
+};
+use bevy::{prelude::*, sprite_render::TilemapChunk};
+
+pub struct BuildPlugin;
+impl Plugin for BuildPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Hotbar>().add_systems(
+            Update,
+            (
+                select_hotbar_slot_system,
+                rotate_build_direction_system,
+                place_structure_on_click_system,
+                deconstruct_structure_on_key_system,
+                update_build_ghost_system,
+            )
+                .chain()
+                .in_set(GameSet::Input)
+                .run_if(in_state(LoadingState::Ready)),
+        );
+    }
+}
+
+pub const HOTBAR_SLOTS: usize = 10;
+const HOTBAR_KEYS: [KeyCode; HOTBAR_SLOTS] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+    KeyCode::Digit0,
+];
+
+/// the item the player is about to place and the direction it will face
+#[derive(Resource)]
+pub struct Hotbar {
+    pub selected: Option<ItemType>,
+    pub direction: Direction,
+}
+impl Default for Hotbar {
+    fn default() -> Self {
+        Self {
+            selected: None,
+            direction: Direction::North,
+        }
+    }
+}
+
+/// the buildable items of the inventory in the order of its slots, without duplicates
+pub fn hotbar_items(
+    inventory: &Inventory,
+    item_registry: &ItemRegistry,
+) -> Vec<(ItemType, StructureKind)> {
+    let mut items: Vec<(ItemType, StructureKind)> = Vec::new();
+    for slot in &inventory.slots {
+        let Some(kind) = item_registry
+            .get(slot.item_type)
+            .and_then(|definition| definition.places)
+        else {
+            continue;
+        };
+        if items.len() < HOTBAR_SLOTS && !items.iter().any(|(item, _)| *item == slot.item_type) {
+            items.push((slot.item_type, kind));
+        }
+    }
+    items
+}
+
+/// ghost of the selected structure on the hovered tile
+#[derive(Component)]
+pub struct BuildGhost;
+impl BuildGhost {
+    pub const LAYER: f32 = 1.0;
+    pub const VALID_COLOR: Color = Color::srgba(1.0, 1.0, 1.0, 0.5);
+    pub const INVALID_COLOR: Color = Color::srgba(1.0, 0.2, 0.2, 0.5);
+}
+
+/// 1-9 and 0 select a slot of the hotbar, the same key or Q puts the item back
+pub fn select_hotbar_slot_system(
+    input: Res<ButtonInput<KeyCode>>,
+    player_query: Query<&Inventory, With<Player>>,
+    item_registry: Res<ItemRegistry>,
+    mut hotbar: ResMut<Hotbar>,
+) {
+    let Ok(inventory) = player_query.single() else {
+        return;
+    };
+    let items = hotbar_items(inventory, &item_registry);
+
+    if input.just_pressed(KeyCode::KeyQ) {
+        hotbar.selected = None;
+    }
+    for (index, key) in HOTBAR_KEYS.iter().enumerate() {
+        if !input.just_pressed(*key) {
+            continue;
+        }
+        let item_type = items.get(index).map(|(item_type, _)| *item_type);
+        hotbar.selected = if hotbar.selected == item_type {
+            None
+        } else {
+            item_type
+        };
+    }
+    // the last one has been placed
+    if hotbar
+        .selected
+        .is_some_and(|selected| !items.iter().any(|(item_type, _)| *item_type == selected))
+    {
+        hotbar.selected = None;
+    }
+}
+
+pub fn rotate_build_direction_system(input: Res<ButtonInput<KeyCode>>, mut hotbar: ResMut<Hotbar>) {
+    if input.just_pressed(KeyCode::KeyR) {
+        hotbar.direction = hotbar.direction.turned_right();
+    }
+}
+
+/// the tile must be in reach, in a loaded chunk, without structure nor unit
+pub fn can_place_on(
+    tile: TileCoordinates,
+    player_tile: TileCoordinates,
+    map_manager: &MapManager,
+    chunk_query: &Query<&StructureLayerManager, With<TilemapChunk>>,
+    unit_query: &Query<(&GridPosition, &CurrentMapId), With<Unit>>,
+) -> bool {
+    Player::can_reach(player_tile, tile)
+        && map_manager
+            .chunks
+            .contains_key(&tile_coord_to_chunk_coord(tile))
+        && map_manager.get_structure(tile, chunk_query).is_none()
+        && !unit_query.iter().any(|(unit_position, unit_map_id)| {
+            unit_map_id.0 == map_manager.map_id && unit_position.0 == tile
+        })
+}
+
+pub fn place_structure_on_click_system(
+    mut commands: Commands,
+    buttons: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    interaction_query: Query<&Interaction>,
+    mut player_query: Query<(&GridPosition, &CurrentMapId, &mut Inventory), With<Player>>,
+    unit_query: Query<(&GridPosition, &CurrentMapId), With<Unit>>,
+    mut chunk_query: Query<&mut StructureLayerManager, With<TilemapChunk>>,
+    (multi_map_manager, hotbar, item_registry, asset_server): (
+        Res<MultiMapManager>,
+        Res<Hotbar>,
+        Res<ItemRegistry>,
+        Res<AssetServer>,
+    ),
+    mut message_recalculate: MessageWriter<RecalculateFlowField>,
+    mut message_recalculate_fov: MessageWriter<RecalculateFov>,
+) {
+    if !buttons.just_pressed(MouseButton::Left) || is_cursor_over_ui(&interaction_query) {
+        return;
+    }
+    let Some(item_type) = hotbar.selected else {
+        return;
+    };
+    let Some(kind) = item_registry
+        .get(item_type)
+        .and_then(|definition| definition.places)
+    else {
+        return;
+    };
+    let (
+        Ok(window),
+        Ok((camera, camera_transform)),
+        Ok((player_position, current_map_id, mut inventory)),
+    ) = (
+        windows.single(),
+        camera_query.single(),
+        player_query.single_mut(),
+    )
+    else {
+        return;
+    };
+    let Some(tile) = cursor_tile(window, camera, camera_transform) else {
+        return;
+    };
+    let Some(map_manager) = multi_map_manager.maps.get(&current_map_id.0) else {
+        return;
+    };
+    if !can_place_on(
+        tile,
+        player_position.0,
+        map_manager,
+        &chunk_query.as_readonly(),
+        &unit_query,
+    ) {
+        return;
+    }
+    if inventory.remove_any_quality(item_type, 1).is_empty() {
+        return;
+    }
+
+    let structure_entity = StructureSave::new_built(kind, hotbar.direction).spawn(
+        GridPosition(tile),
+        &mut commands,
+        &asset_server,
+    );
+    commands
+        .entity(structure_entity)
+        .insert(CurrentMapId(current_map_id.0));
+    map_manager.insert_structure(tile, structure_entity, &mut chunk_query, &mut commands);
+    message_recalculate.write_default();
+    message_recalculate_fov.write_default();
+}
+
+/// X deconstructs the hovered structure in reach, its build item and its contents go to the player inventory
+///
+/// portals have no build item, removing one only closes its way: the destination map and a portal leading back stay
+pub fn deconstruct_structure_on_key_system(
+    mut commands: Commands,
+    input: Res<ButtonInput<KeyCode>>,
+    windows: Query<&Window>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    mut player_query: Query<(&GridPosition, &CurrentMapId, &mut Inventory), With<Player>>,
+    structure_query: Query<StructureSaveQuery, (With<Structure>, Without<Player>)>,
+    mut chunk_query: Query<&mut StructureLayerManager, With<TilemapChunk>>,
+    (multi_map_manager, item_registry, mut opened_chest): (
+        Res<MultiMapManager>,
+        Res<ItemRegistry>,
+        ResMut<OpenedChest>,
+    ),
+    mut message_recalculate: MessageWriter<RecalculateFlowField>,
+    mut message_recalculate_fov: MessageWriter<RecalculateFov>,
+) {
+    if !input.just_pressed(KeyCode::KeyX) {
+        return;
+    }
+    let (
+        Ok(window),
+        Ok((camera, camera_transform)),
+        Ok((player_position, current_map_id, mut inventory)),
+    ) = (
+        windows.single(),
+        camera_query.single(),
+        player_query.single_mut(),
+    )
+    else {
+        return;
+    };
+    let Some(tile) = cursor_tile(window, camera, camera_transform) else {
+        return;
+    };
+    let Some(map_manager) = multi_map_manager.maps.get(&current_map_id.0) else {
+        return;
+    };
+    if !Player::can_reach(player_position.0, tile) {
+        return;
+    }
+    let Some(structure_entity) = map_manager.get_structure(tile, &chunk_query.as_readonly()) else {
+        return;
+    };
+    let Some(structure_save) = structure_query
+        .get(structure_entity)
+        .ok()
+        .and_then(|item| StructureSave::capture(&item))
+    else {
+        return;
+    };
+
+    // the structure stays if everything doesn't fit, nothing is lost
+    let mut items = structure_save.contents();
+    if let Some(kind) = structure_save.kind() {
+        let Some(build_item) = item_registry.item_placing(kind) else {
+            return;
+        };
+        items.push(ItemStack::new(build_item, Quality::Standard, 1));
+    }
+    let mut new_inventory = inventory.clone();
+    if items
+        .into_iter()
+        .any(|item_stack| new_inventory.insert(item_stack, &item_registry).is_some())
+    {
+        info!("Not enough room in the inventory to deconstruct the structure");
+        return;
+    }
+    *inventory = new_inventory;
+
+    map_manager.remove_structure(tile, &mut chunk_query);
+    commands.entity(structure_entity).despawn();
+    if opened_chest.0 == Some(structure_entity) {
+        opened_chest.0 = None;
+    }
+    message_recalculate.write_default();
+    message_recalculate_fov.write_default();
+}
+
+pub fn update_build_ghost_system(
+    mut commands: Commands,
+    windows: Query<&Window>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    player_query: Query<(&GridPosition, &CurrentMapId), With<Player>>,
+    unit_query: Query<(&GridPosition, &CurrentMapId), With<Unit>>,
+    chunk_query: Query<&StructureLayerManager, With<TilemapChunk>>,
+    mut ghost_query: Query<(&mut Transform, &mut Sprite, &mut Visibility), With<BuildGhost>>,
+    (multi_map_manager, hotbar, item_registry, asset_server): (
+        Res<MultiMapManager>,
+        Res<Hotbar>,
+        Res<ItemRegistry>,
+        Res<AssetServer>,
+    ),
+) {
+    let Ok((mut transform, mut sprite, mut visibility)) = ghost_query.single_mut() else {
+        commands.spawn((
+            Sprite::default(),
+            Transform::default(),
+            Visibility::Hidden,
+            BuildGhost,
+        ));
+        return;
+    };
+
+    let kind = hotbar
+        .selected
+        .and_then(|item_type| item_registry.get(item_type))
+        .and_then(|definition| definition.places);
+    let (
+        Some(kind),
+        Ok(window),
+        Ok((camera, camera_transform)),
+        Ok((player_position, current_map_id)),
+    ) = (
+        kind,
+        windows.single(),
+        camera_query.single(),
+        player_query.single(),
+    )
+    else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+    let (Some(tile), Some(map_manager)) = (
+        cursor_tile(window, camera, camera_transform),
+        multi_map_manager.maps.get(&current_map_id.0),
+    ) else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+
+    let absolute_coordinates = tile_coord_to_absolute_coord(tile);
+    transform.translation = Vec3::new(
+        absolute_coordinates.x,
+        absolute_coordinates.y,
+        BuildGhost::LAYER,
+    );
+    transform.rotation = hotbar.direction.to_rotation();
+    sprite.image = asset_server.load(Structure::PATH_PNG_FOLDER.to_owned() + kind.png_file_name());
+    sprite.color = if can_place_on(
+        tile,
+        player_position.0,
+        map_manager,
+        &chunk_query,
+        &unit_query,
+    ) {
+        BuildGhost::VALID_COLOR
+    } else {
+        BuildGhost::INVALID_COLOR
+    };
+    *visibility = Visibility::Visible;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::items::registry::{ItemCategory, ItemDefinition};
+
+    #[test]
+    fn test_hotbar_items() {
+        let definition = |item_type: ItemType, places| ItemDefinition {
+            category: ItemCategory::Building,
+            places,
+            ..ItemDefinition::test(item_type, 10)
+        };
+        let item_registry = ItemRegistry::from_definitions(vec![
+            definition(ItemType::IRON_PLATE, None),
+            definition(ItemType::BELT, Some(StructureKind::Belt)),
+            definition(ItemType::CHEST, Some(StructureKind::Chest)),
+        ])
+        .unwrap();
+        let inventory = Inventory {
+            slots: vec![
+                ItemStack::new(ItemType::CHEST, Quality::Standard, 1),
+                ItemStack::new(ItemType::IRON_PLATE, Quality::Standard, 5),
+                ItemStack::new(ItemType::BELT, Quality::Standard, 10),
+                ItemStack::new(ItemType::CHEST, Quality::Perfect, 1),
+            ],
+            ..default()
+        };
+
+        assert_eq!(
+            hotbar_items(&inventory, &item_registry),
+            vec![
+                (ItemType::CHEST, StructureKind::Chest),
+                (ItemType::BELT, StructureKind::Belt)
+            ]
+        );
+    }
+}
@@ -139,22 +139,26 @@ fn transform_octant(octant: usize, origin: TileCoordinates, x: i32, y: i32) -> T
     }
 }
 
+/// the fov is also recalculated when the player moves or turns
+#[derive(Message, Default)]
+pub struct RecalculateFov;
+
 pub fn update_fov_system(
-    mut player_query: Query<
-        (&GridPosition, &CurrentMapId, &Direction),
-        (
-            With<Player>,
-            Or<(Changed<GridPosition>, Changed<Direction>)>,
-        ),
-    >,
+    mut player_query: Query<(Ref<GridPosition>, &CurrentMapId, Ref<Direction>), With<Player>>,
+    mut message_recalculate_fov: MessageReader<RecalculateFov>,
     multi_map_manager: Res<MultiMapManager>,
     mut chunk_query: Query<&mut ChunkFogOfWar, With<TilemapChunk>>,
     chunk_structure_query: Query<&StructureLayerManager, With<TilemapChunk>>,
     block_sight_structure_query: Query<(), (With<BlockSight>, With<Structure>)>,
 ) {
+    // a structure blocking sight may have been built or removed
+    let fov_outdated = message_recalculate_fov.read().count() > 0;
     let Ok((player_pos, map_id, facing_direction)) = player_query.single_mut() else {
         return;
     };
+    if !fov_outdated && !player_pos.is_changed() && !facing_direction.is_changed() {
+        return;
+    }
 
     let Some(map_manager) = multi_map_manager.maps.get(&map_id.0) else {
         return;
@@ -0,0 +1,138 @@
+use crate::{
+    FixedSet,
+    direction::Direction,
+    items::{inventory::Inventory, production::ProductionStats, registry::ItemRegistry},
+    loading::LoadingState,
+    map::{
+        CurrentMapId, MultiMapManager, ResourceNodeLayerManager, coordinates::GridPosition,
+        resource_node::ResourceNode, structure::belt::tile_in_front,
+    },
+    time::GameTime,
+    units::{Player, Unit},
+};
+use bevy::{prelude::*, sprite_render::TilemapChunk};
+use serde::{Deserialize, Serialize};
+
+pub struct MiningPlugin;
+impl Plugin for MiningPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            player_manual_mining_system
+                .in_set(FixedSet::Process)
+                .run_if(in_state(LoadingState::Ready)),
+        );
+    }
+}
+
+/// multiplier of the mining progress made each tick
+#[derive(Component, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MiningSpeedStat(pub f32);
+impl Default for MiningSpeedStat {
+    fn default() -> Self {
+        Self(Unit::DEFAULT_MINING_SPEED)
+    }
+}
+
+/// progress of the player mining a resource node by hand
+#[derive(Component, Debug, Default)]
+pub struct ManualMining {
+    pub progress_ticks: f32,
+}
+impl ManualMining {
+    /// slower than a mining machine
+    pub const ACTION_TIME_TICKS: f32 = GameTime::TICKS_PER_SECOND as f32 * 2.0;
+
+    /// returns true when an item has been extracted, the progress is then reset
+    pub fn advance(&mut self, mining_speed: MiningSpeedStat) -> bool {
+        self.progress_ticks += mining_speed.0;
+        if self.progress_ticks < Self::ACTION_TIME_TICKS {
+            return false;
+        }
+        self.progress_ticks = 0.0;
+        true
+    }
+}
+
+/// holding F mines the node under the player, or the node in front of them
+pub fn player_manual_mining_system(
+    mut commands: Commands,
+    input: Res<ButtonInput<KeyCode>>,
+    mut player_query: Query<
+        (
+            &GridPosition,
+            &CurrentMapId,
+            &Direction,
+            &MiningSpeedStat,
+            &mut ManualMining,
+            &mut Inventory,
+        ),
+        With<Player>,
+    >,
+    mut resource_node_chunk_query: Query<&mut ResourceNodeLayerManager, With<TilemapChunk>>,
+    mut resource_node_query: Query<&mut ResourceNode>,
+    multi_map_manager: Res<MultiMapManager>,
+    item_registry: Res<ItemRegistry>,
+    mut production_stats: ResMut<ProductionStats>,
+) {
+    let Ok((
+        grid_position,
+        current_map_id,
+        direction,
+        mining_speed,
+        mut manual_mining,
+        mut inventory,
+    )) = player_query.single_mut()
+    else {
+        return;
+    };
+    let Some(map_manager) = multi_map_manager.maps.get(&current_map_id.0) else {
+        return;
+    };
+    let target = [grid_position.0, tile_in_front(grid_position.0, *direction)]
+        .into_iter()
+        .find_map(|tile| {
+            map_manager
+                .get_resource_node(tile, &resource_node_chunk_query.as_readonly())
+                .map(|entity| (tile, entity))
+        });
+
+    let Some((tile, resource_node_entity)) = target.filter(|_| input.pressed(KeyCode::KeyF)) else {
+        manual_mining.progress_ticks = 0.0;
+        return;
+    };
+    let Ok(mut resource_node) = resource_node_query.get_mut(resource_node_entity) else {
+        return;
+    };
+
+    // one item at a time, it waits for room in the inventory
+    let Some(mined_item) = resource_node.peek(1) else {
+        return;
+    };
+    if !inventory.enough_room(mined_item, &item_registry) {
+        return;
+    }
+    if manual_mining.advance(*mining_speed) {
+        resource_node.extract(1);
+        inventory.insert(mined_item, &item_registry);
+        production_stats.record_produced(mined_item);
+        if resource_node.is_depleted() {
+            map_manager.remove_resource_node(tile, &mut resource_node_chunk_query);
+            commands.entity(resource_node_entity).despawn();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manual_mining_advance() {
+        let mut manual_mining = ManualMining::default();
+        let mining_speed = MiningSpeedStat(ManualMining::ACTION_TIME_TICKS / 2.0);
+        assert!(!manual_mining.advance(mining_speed));
+        assert!(manual_mining.advance(mining_speed));
+        assert_eq!(manual_mining.progress_ticks, 0.0);
+    }
+}
@@ -1,6 +1,9 @@
+pub mod build;
 pub mod fov;
+pub mod mining;
 pub mod pathfinding;
 mod player;
+pub mod save_units;
 mod unit;
 
 pub use player::*;
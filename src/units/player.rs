@@ -2,9 +2,11 @@ use std::collections::VecDeque;
 
 use bevy::{prelude::*, sprite_render::TilemapChunk};
 use pathfinding::prelude::astar;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     direction::Direction,
+    items::inventory::Inventory,
     map::{
         CurrentMapId, MultiMapManager, StructureLayerManager,
         coordinates::{
@@ -13,10 +15,10 @@ use crate::{
         structure::Structure,
     },
     physics::movement::{DesiredMovement, MovementAccumulator, Passable},
-    units::{UnitBundle, pathfinding::RecalculateFlowField},
+    units::{UnitBundle, mining::ManualMining, pathfinding::RecalculateFlowField},
 };
 
-#[derive(Component, Default, Debug)]
+#[derive(Component, Default, Debug, Clone, Serialize, Deserialize)]
 pub struct PlayerPath {
     waypoints: VecDeque<TileCoordinates>,
 }
@@ -38,11 +40,21 @@ impl PlayerPath {
 pub struct Player;
 impl Player {
     pub const PATH_PNG: &'static str = "units/player.png";
+    pub const INVENTORY_SLOTS: u32 = 20;
+    /// chebyshev distance in tiles up to which the player can use a structure
+    pub const REACH_TILES: i32 = 3;
+
+    pub fn can_reach(player_tile: TileCoordinates, tile: TileCoordinates) -> bool {
+        (player_tile.x - tile.x).abs() <= Self::REACH_TILES
+            && (player_tile.y - tile.y).abs() <= Self::REACH_TILES
+    }
 }
 #[derive(Bundle)]
 pub struct PlayerBundle {
     pub base: UnitBundle,
     pub path: PlayerPath,
+    pub inventory: Inventory,
+    pub manual_mining: ManualMining,
     pub player: Player,
 }
 impl PlayerBundle {
@@ -50,6 +62,8 @@ impl PlayerBundle {
         Self {
             base,
             path: PlayerPath::default(),
+            inventory: Inventory::with_slots(Player::INVENTORY_SLOTS),
+            manual_mining: ManualMining::default(),
             player: Player,
         }
     }
@@ -1,116 +1,110 @@
-// save
-// #[derive(Debug, Clone, Serialize, Deserialize)]
-// pub struct SavedUnit {
-//     pub name: Name,
-//     pub transform: Transform,
-//     pub direction: Direction,
-//     pub speed: Speed,
-//     pub linear_velocity: LinearVelocity,
-//     pub is_player: bool,
-//     pub inventory: Option<Inventory>,
-// }
-// #[derive(Serialize, Deserialize)]
-// pub struct MapSaveData {
-//     pub map_name: String,
-//     pub units: Vec<SavedUnit>,
-//     pub version: f32,
-// }
+use crate::{
+    direction::Direction,
+    items::inventory::Inventory,
+    map::{
+        CurrentMapId, MapId,
+        coordinates::{GridPosition, TileCoordinates},
+    },
+    physics::movement::SpeedStat,
+    units::{
+        Player, PlayerBundle, PlayerPath, Unit, UnitBundle,
+        mining::{ManualMining, MiningSpeedStat},
+    },
+};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
-// pub fn save_units_to_file_system(
-//     units_query: Query<
-//         (
-//             &Name,
-//             &Transform,
-//             &Direction,
-//             &Speed,
-//             &LinearVelocity,
-//             Has<Player>,
-//             Option<&Inventory>,
-//         ),
-//         With<Unit>,
-//     >,
-//     map_name: String,
-// ) {
-//     let mut saved_units = Vec::new();
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnitSave {
+    pub name: String,
+    pub grid_position: TileCoordinates,
+    pub current_map_id: MapId,
+    pub speed_stat: SpeedStat,
+    #[serde(default)]
+    pub mining_speed_stat: MiningSpeedStat,
+    pub direction: Direction,
+    /// only the player has a path, so Some means the unit is the player
+    pub player_path: Option<PlayerPath>,
+    #[serde(default)]
+    pub inventory: Option<Inventory>,
+}
 
-//     for (name, transform, direction, speed, linear_velocity, is_player, inventory) in
-//         units_query.iter()
-//     {
-//         saved_units.push(SavedUnit {
-//             name: name.clone(),
-//             transform: *transform,
-//             direction: *direction,
-//             speed: *speed,
-//             linear_velocity: *linear_velocity,
-//             is_player,
-//             inventory: inventory.cloned(),
-//         })
-//     }
+pub type UnitSaveQuery = (
+    &'static Name,
+    &'static GridPosition,
+    &'static CurrentMapId,
+    &'static SpeedStat,
+    &'static MiningSpeedStat,
+    &'static Direction,
+    Option<&'static PlayerPath>,
+    Option<&'static Inventory>,
+);
 
-//     let save_data = MapSaveData {
-//         map_name: map_name.clone(),
-//         units: saved_units,
-//         version: CURRENT_SAVE_VERSION,
-//     };
+impl UnitSave {
+    pub fn capture(
+        (
+            name,
+            grid_position,
+            current_map_id,
+            speed_stat,
+            mining_speed_stat,
+            direction,
+            player_path,
+            inventory,
+        ): (
+            &Name,
+            &GridPosition,
+            &CurrentMapId,
+            &SpeedStat,
+            &MiningSpeedStat,
+            &Direction,
+            Option<&PlayerPath>,
+            Option<&Inventory>,
+        ),
+    ) -> Self {
+        Self {
+            name: name.as_str().to_owned(),
+            grid_position: grid_position.0,
+            current_map_id: current_map_id.0,
+            speed_stat: *speed_stat,
+            mining_speed_stat: *mining_speed_stat,
+            direction: *direction,
+            player_path: player_path.cloned(),
+            inventory: inventory.cloned(),
+        }
+    }
 
-//     match serde_json::to_string_pretty(&save_data) {
-//         Ok(json) => {
-//             if let Err(e) = fs::write(format!("{}/{}.json", PATH_SAVES, map_name), json) {
-//                 error!("Erreur lors de la sauvegarde: {}", e);
-//             } else {
-//                 info!("Sauvegardé {} unités", save_data.units.len());
-//             }
-//         }
-//         Err(e) => error!("Erreur de sérialisation: {}", e),
-//     }
-// }
+    pub fn spawn(self, commands: &mut Commands, asset_server: &AssetServer) -> Entity {
+        let mut unit_bundle = UnitBundle::new(
+            Name::new(self.name),
+            GridPosition(self.grid_position),
+            CurrentMapId(self.current_map_id),
+            self.speed_stat,
+        );
+        unit_bundle.direction = self.direction;
+        unit_bundle.mining_speed_stat = self.mining_speed_stat;
 
-// pub fn load_units_from_file_system(
-//     commands: &mut Commands,
-//     asset_server: &Res<AssetServer>,
-//     map_name: String,
-// ) {
-//     let path = format!("{}/{}.json", PATH_SAVES, map_name);
-//     let save_path = Path::new(&path);
-
-//     if !save_path.exists() {
-//         panic!("Can't find {}", path);
-//     }
-
-//     match fs::read_to_string(save_path) {
-//         Ok(json_content) => match serde_json::from_str::<MapSaveData>(&json_content) {
-//             Ok(save_data) => {
-//                 let texture_handle = asset_server.load("default.png");
-
-//                 for SavedUnit {
-//                     name,
-//                     transform,
-//                     direction,
-//                     speed,
-//                     linear_velocity,
-//                     is_player,
-//                     inventory,
-//                 } in save_data.units
-//                 {
-//                     let bundle = UnitBundle::new(name, transform, speed);
-//                     let mut entity = commands.spawn((
-//                         bundle,
-//                         direction,
-//                         linear_velocity,
-//                         Sprite::from_image(texture_handle.clone()),
-//                     ));
-
-//                     if is_player {
-//                         entity.insert(Player);
-//                     }
-
-//                     if let Some(some_inventory) = inventory {
-//                         entity.insert(some_inventory);
-//                     }
-//                 }
-//             }
-//             Err(e) => error!("Error deserializing: {}", e),
-//         },
-//         Err(e) => error!("Error reading file: {}", e),
-//     }
-// }
+        match self.player_path {
+            Some(path) => commands
+                .spawn((
+                    PlayerBundle {
+                        base: unit_bundle,
+                        path,
+                        inventory: self
+                            .inventory
+                            .unwrap_or_else(|| Inventory::with_slots(Player::INVENTORY_SLOTS)),
+                        manual_mining: ManualMining::default(),
+                        player: Player,
+                    },
+                    Sprite::from_image(asset_server.load(Player::PATH_PNG)),
+                ))
+                .id(),
+            None => commands
+                .spawn((
+                    unit_bundle,
+                    Sprite::from_image(asset_server.load(Unit::PATH_PNG)),
+                ))
+                .id(),
+        }
+    }
+}
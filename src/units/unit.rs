@@ -11,7 +11,7 @@ use crate::{
         collision_event::CollisionHistory,
         movement::{DesiredMovement, MovementAccumulator, SpeedStat},
     },
-    units::{pathfinding::FlowField, player::Player},
+    units::{mining::MiningSpeedStat, pathfinding::FlowField, player::Player},
 };
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
@@ -23,6 +23,7 @@ impl Unit {
     pub const DEFAULT_SCALE_MULTIPLIER: f32 = 0.8;
     pub const DEFAULT_SIZE: f32 = TILE_SIZE.x as f32 * Unit::DEFAULT_SCALE_MULTIPLIER;
     pub const DEFAULT_TILE_PER_SECOND_SPEED: f32 = 8.0;
+    pub const DEFAULT_MINING_SPEED: f32 = 1.0;
     pub const DEFAULT_LAYER: f32 = 1.0;
     pub const PATH_PNG: &'static str = "default.png";
 }
@@ -35,6 +36,7 @@ pub struct UnitBundle {
     pub current_map_id: CurrentMapId,
     pub direction: Direction,
     pub speed_stat: SpeedStat,
+    pub mining_speed_stat: MiningSpeedStat,
     pub movement_accumulator: MovementAccumulator,
     pub desired_movement: DesiredMovement,
     pub collision_history: CollisionHistory,
@@ -61,6 +63,7 @@ impl UnitBundle {
             current_map_id,
             direction: Direction::East,
             speed_stat,
+            mining_speed_stat: MiningSpeedStat::default(),
             movement_accumulator: MovementAccumulator::default(),
             collision_history: CollisionHistory::default(),
             desired_movement: DesiredMovement::default(),